@@ -1,39 +1,305 @@
 use fyrox_sound::{
+    algebra::Vector3,
     buffer::{DataSource, SoundBufferResource, SoundBufferResourceExtension},
-    context::SoundContext,
+    context::{DistanceModel, SoundContext},
     engine::SoundEngine,
     pool::Handle,
     source::{SoundSource, SoundSourceBuilder, Status}
 };
 use std::collections::HashMap;
 
+use crate::globals::BOARD_WIDTH;
+use crate::synth;
+
+// seconds to fade between the menu and level tracks
+const CROSSFADE_TIME: f32 = 2.;
+// seconds for a single stem to fade in or out
+const STEM_FADE_TIME: f32 = 1.5;
+// sources sharing the buffer of a single sound
+const VOICES_PER_SOUND: usize = 3;
+// effects audible at once, quieter ones are cut first
+const MAX_VOICES: usize = 8;
+// the listener sits in front of the board centre, so that the panning
+// stays soft and sounds just off the screen are quieter but audible
+const LISTENER_DEPTH: f32 = 4.;
+// distance from the sound at which the attenuation starts
+const SOUND_RADIUS: f32 = 4.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    // a taxi damaged by a hard landing or a bird
+    Hit,
+    // two taxis colliding
+    Bump,
+    KnockDown,
+    Load,
+    Unload,
+    Resign,
+    WingFlap,
+    MammothBlow,
+    // a menu entry activated
+    Select
+}
+impl SoundEvent {
+    fn spec(&self) -> EventSpec {
+        match self {
+            SoundEvent::Hit => EventSpec::new("hit", Bus::Sfx, 3).with_cooldown(0.1),
+            SoundEvent::Bump => EventSpec::new("hit", Bus::Sfx, 2)
+                .with_cooldown(0.15)
+                .with_gain(0.7),
+            SoundEvent::KnockDown => EventSpec::new("hit", Bus::Sfx, 2).with_pitch(1.3),
+            SoundEvent::Load => EventSpec::new("load", Bus::Sfx, 2),
+            SoundEvent::Unload => EventSpec::new("unload", Bus::Sfx, 4),
+            SoundEvent::Resign => EventSpec::new("resign", Bus::Sfx, 4).with_cooldown(0.3),
+            SoundEvent::WingFlap => EventSpec::new("flap", Bus::Sfx, 0)
+                .with_cooldown(0.)
+                .with_gain(0.5),
+            SoundEvent::MammothBlow => EventSpec::new("blow", Bus::Sfx, 1).with_cooldown(0.6),
+            SoundEvent::Select => EventSpec::new("load", Bus::Ui, 1)
+                .with_pitch(1.6)
+                .with_gain(0.5)
+                .with_variation(0., 0.)
+        }
+    }
+}
+
+struct EventSpec {
+    sound: &'static str,
+    bus: Bus,
+    // a new sound can only cut off voices of the same or lower priority
+    priority: u32,
+    // seconds before the event can be heard again
+    cooldown: f32,
+    gain: f32,
+    pitch: f32,
+    // random +/- share applied on every play
+    gain_variation: f32,
+    pitch_variation: f32
+}
+impl EventSpec {
+    fn new(sound: &'static str, bus: Bus, priority: u32) -> Self {
+        EventSpec {
+            sound,
+            bus,
+            priority,
+            cooldown: 0.05,
+            gain: 1.,
+            pitch: 1.,
+            gain_variation: 0.1,
+            pitch_variation: 0.08
+        }
+    }
+    fn with_cooldown(mut self, cooldown: f32) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+    fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+    fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+    fn with_variation(mut self, gain: f32, pitch: f32) -> Self {
+        self.gain_variation = gain;
+        self.pitch_variation = pitch;
+        self
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Bus {
+    Sfx,
+    Ui,
+    Music
+}
+
+struct Mixer {
+    sfx: f32,
+    ui: f32,
+    music: f32
+}
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer { sfx: 1., ui: 1., music: 1. }
+    }
+}
+impl Mixer {
+    fn gain(&self, bus: Bus) -> f32 {
+        match bus {
+            Bus::Sfx => self.sfx,
+            Bus::Ui => self.ui,
+            Bus::Music => self.music
+        }
+    }
+}
+
+struct Voice {
+    handle: Handle<SoundSource>,
+    priority: u32
+}
+
+struct Stem {
+    handle: Handle<SoundSource>,
+    // intensity at which the stem becomes audible
+    from: f32,
+    level: f32
+}
+
+struct Track {
+    // all the stems loop in sync, so they start and pause together
+    stems: Vec<Stem>,
+    fade: f32
+}
 
 #[derive(Default)]
 pub struct AudioContext {
     inner: Option<SoundContext>,
-    sounds: HashMap<&'static str, Handle<SoundSource>>
+    // a pool of voices per sound
+    sounds: HashMap<String, Vec<Handle<SoundSource>>>,
+    // voices started by events that may still be playing
+    voices: Vec<Voice>,
+    last_played: HashMap<SoundEvent, f32>,
+    // seconds since start, for the cooldowns
+    clock: f32,
+    mixer: Mixer,
+    tracks: HashMap<String, Track>
 }
 impl AudioContext {
-    pub fn play(&mut self, sound: &str) {
-        let Some(handle) = self.sounds.get(sound) else { return };
+    pub fn emit(&mut self, event: SoundEvent) {
+        self.play(event, None);
+    }
+    pub fn emit_at(&mut self, event: SoundEvent, x: f32) {
+        // panned and attenuated by the world x position
+        self.play(event, Some(x));
+    }
+    fn play(&mut self, event: SoundEvent, x: Option<f32>) {
+        let spec = event.spec();
+        if let Some(last) = self.last_played.get(&event) {
+            if self.clock - last < spec.cooldown { return }
+        }
+        let Some(context) = self.inner.as_mut() else { return };
+        let Some(pool) = self.sounds.get(spec.sound) else { return };
+        let mut state = context.state();
+        self.voices.retain(|a| state.source(a.handle).status() == Status::Playing);
+
+        // a free voice of the sound, or else its least important one
+        let handle = match pool.iter().find(|h| !self.voices.iter().any(|a| a.handle == **h)) {
+            Some(handle) => *handle,
+            None => {
+                let Some(voice) = self.voices.iter()
+                    .filter(|a| pool.contains(&a.handle))
+                    .min_by_key(|a| a.priority) else { return };
+                voice.handle
+            }
+        };
+        if !self.voices.iter().any(|a| a.handle == handle) && self.voices.len() >= MAX_VOICES {
+            // over the global limit a voice of another sound has to go
+            let Some(voice) = self.voices.iter().min_by_key(|a| a.priority) else { return };
+            if voice.priority > spec.priority { return }
+            let _ = state.source_mut(voice.handle).stop();
+            let stolen = voice.handle;
+            self.voices.retain(|a| a.handle != stolen);
+        }
+        if let Some(idx) = self.voices.iter().position(|a| a.handle == handle) {
+            if self.voices[idx].priority > spec.priority { return }
+            self.voices.remove(idx);
+        }
+
+        let gain = spec.gain * vary(spec.gain_variation) * self.mixer.gain(spec.bus);
+        let pitch = spec.pitch * vary(spec.pitch_variation);
+        let source = state.source_mut(handle);
+        let _ = source.stop();
+        source.set_gain(gain);
+        source.set_pitch(pitch as f64);
+        match x {
+            Some(x) => {
+                source.set_spatial_blend(1.);
+                source.set_position(Vector3::new(x, 0., 0.));
+            },
+            None => { source.set_spatial_blend(0.); }
+        }
+        source.play();
+        self.voices.push(Voice { handle, priority: spec.priority });
+        self.last_played.insert(event, self.clock);
+    }
+    pub fn tick(&mut self, delta: f32) {
+        self.clock += delta;
+    }
+    pub fn set_volume(&mut self, master: f32, sfx: f32, music: f32) {
+        // the menu sounds follow the effects volume
+        self.mixer = Mixer { sfx, ui: sfx, music };
         if let Some(context) = self.inner.as_mut() {
-            let mut state = context.state();
-            let source = state.source_mut(*handle);
-            let _ = source.stop();
-            source.play();
+            context.state().set_master_gain(master);
+        }
+    }
+    pub fn add_track(&mut self, name: &str, stems: Vec<(Vec<u8>, f32)>) {
+        // stems are (encoded data, intensity threshold)
+        let Some(context) = self.inner.as_mut() else { return };
+        let mut track = Track { stems: Vec::new(), fade: 0. };
+        for (data, from) in stems {
+            let Ok(buffer) = SoundBufferResource::new_streaming(
+                DataSource::from_memory(data)
+            ) else { continue };
+            let Ok(source) = SoundSourceBuilder::new()
+                .with_buffer(buffer)
+                .with_looping(true)
+                .with_spatial_blend_factor(0.)
+                .with_gain(0.)
+                .with_status(Status::Paused)
+                .build() else { continue };
+            let handle = context.state().add_source(source);
+            track.stems.push(Stem { handle, from, level: 0. });
+        }
+        if track.stems.len() > 0 {
+            self.tracks.insert(name.to_string(), track);
+        }
+    }
+    pub fn update_music(&mut self, current: Option<&str>, intensity: f32, delta: f32) {
+        // should be called every frame, with the intensity between 0 and 1
+        let Some(context) = self.inner.as_mut() else { return };
+        let mut state = context.state();
+        for (name, track) in self.tracks.iter_mut() {
+            let target = if Some(name.as_str()) == current { 1. } else { 0. };
+            track.fade = approach(track.fade, target, delta / CROSSFADE_TIME);
+            let silent = track.fade == 0. && target == 0.;
+            for stem in track.stems.iter_mut() {
+                let level = if intensity >= stem.from { 1. } else { 0. };
+                stem.level = approach(stem.level, level, delta / STEM_FADE_TIME);
+                let source = state.source_mut(stem.handle);
+                source.set_gain(self.mixer.gain(Bus::Music) * track.fade * stem.level);
+                match (silent, source.status()) {
+                    (true, Status::Playing) => { source.pause(); },
+                    (false, Status::Paused) | (false, Status::Stopped) => { source.play(); },
+                    _ => ()
+                }
+            }
         }
     }
 }
 
+fn vary(variation: f32) -> f32 {
+    // a random multiplier around 1
+    1. + variation * (2. * rand::random::<f32>() - 1.)
+}
+
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target { return (value + step).min(target) }
+    (value - step).max(target)
+}
+
 pub fn get_audio_context() -> AudioContext {
-    let Ok(engine) = SoundEngine::new() else {
-        return AudioContext {
-            inner: None,
-            sounds: HashMap::new()
-        }
-    };
+    let Ok(engine) = SoundEngine::new() else { return AudioContext::default() };
     let context = SoundContext::new();
     engine.state().add_context(context.clone());
+    {
+        let mut state = context.state();
+        state.set_distance_model(DistanceModel::InverseDistance);
+        state.listener_mut().set_position(
+            Vector3::new(BOARD_WIDTH as f32 / 2., 0., -LISTENER_DEPTH)
+        );
+    }
 
     let mut data = HashMap::new();
     data.insert("hit", include_bytes!("../assets/hit.wav").to_vec());
@@ -41,20 +307,39 @@ pub fn get_audio_context() -> AudioContext {
     data.insert("unload", include_bytes!("../assets/unload.wav").to_vec());
     data.insert("resign", include_bytes!("../assets/resign.wav").to_vec());
 
+    // sounds without an asset are synthesized
+    let presets = synth::load_presets();
+    for name in presets.keys() {
+        data.entry(name.as_str()).or_insert_with(Vec::new);
+    }
+
     let mut sounds = HashMap::new();
 
     for (k, v) in data.iter() {
-        let buffer = SoundBufferResource::new_generic(
-                DataSource::from_memory(v.to_vec())
-            )
-            .expect(&format!("Can't build audio buffer for {}!", k));
-        let source = SoundSourceBuilder::new()
-            .with_buffer(buffer)
-            .build()
-            .expect(&format!("Can't build audio source for {}!", k));
-        let handle = context.state().add_source(source);
-        sounds.insert(*k, handle);
-    }
-
-    AudioContext { inner: Some(context), sounds }
+        let buffer = match SoundBufferResource::new_generic(DataSource::from_memory(v.to_vec())) {
+            Ok(buffer) => buffer,
+            Err(_) => {
+                // empty or broken files fall back to the synthesizer
+                let Some(params) = presets.get(*k) else { continue };
+                let wav = synth::to_wav(&synth::generate(params));
+                let Ok(buffer) = SoundBufferResource::new_generic(DataSource::from_memory(wav)) else { continue };
+                buffer
+            }
+        };
+        let pool = (0..VOICES_PER_SOUND).filter_map(|_| {
+            let source = SoundSourceBuilder::new()
+                .with_buffer(buffer.clone())
+                .with_radius(SOUND_RADIUS)
+                .build()
+                .ok()?;
+            Some(context.state().add_source(source))
+        }).collect();
+        sounds.insert(k.to_string(), pool);
+    }
+
+    AudioContext {
+        inner: Some(context),
+        sounds,
+        ..Default::default()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+use rogalik_engine::Color;
+use rogalik_math::vectors::Vector2i;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Deserialize)]
+enum Neighbour {
+    N, NE, E, SE, S, SW, W, NW
+}
+impl Neighbour {
+    fn offset(&self) -> Vector2i {
+        match self {
+            Neighbour::N => Vector2i::new(0, 1),
+            Neighbour::NE => Vector2i::new(1, 1),
+            Neighbour::E => Vector2i::new(1, 0),
+            Neighbour::SE => Vector2i::new(1, -1),
+            Neighbour::S => Vector2i::new(0, -1),
+            Neighbour::SW => Vector2i::new(-1, -1),
+            Neighbour::W => Vector2i::new(-1, 0),
+            Neighbour::NW => Vector2i::new(-1, 1)
+        }
+    }
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Rule {
+    solid: Vec<Neighbour>,
+    empty: Vec<Neighbour>,
+    index: usize
+}
+impl Rule {
+    fn matches(&self, mask: u8) -> bool {
+        self.solid.iter().all(|a| mask & a.bit() != 0)
+            && self.empty.iter().all(|a| mask & a.bit() == 0)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Family {
+    pub name: String,
+    // the level file character
+    pub symbol: char,
+    #[serde(with = "crate::save::atlas")]
+    pub atlas: &'static str,
+    // atlas index of the first sprite of the rule table
+    base: usize,
+    color: (u8, u8, u8, u8),
+    rules: String
+}
+impl Default for Family {
+    fn default() -> Self {
+        Family {
+            name: String::new(),
+            symbol: '#',
+            atlas: "tiles",
+            base: 0,
+            color: (255, 255, 255, 255),
+            rules: String::new()
+        }
+    }
+}
+impl Family {
+    pub fn color(&self) -> Color {
+        let (r, g, b, a) = self.color;
+        Color(r, g, b, a)
+    }
+}
+
+#[derive(Default, Deserialize)]
+pub struct Tileset {
+    // rule tables by name, shared by the families
+    rules: HashMap<String, Vec<Rule>>,
+    pub families: Vec<Family>
+}
+impl Tileset {
+    pub fn sprite_index(&self, family: &Family, v: Vector2i, cells: &HashSet<Vector2i>) -> usize {
+        // `cells` are the positions of the same family
+        let mask = neighbour_mask(v, cells);
+        let index = self.rules.get(&family.rules)
+            .and_then(|a| a.iter().find(|r| r.matches(mask)))
+            .map(|a| a.index)
+            .unwrap_or(0);
+        family.base + index
+    }
+}
+
+pub fn load_tileset() -> Tileset {
+    ron::from_str(include_str!("../assets/autotile.ron")).expect("Invalid autotile data!")
+}
+
+fn neighbour_mask(v: Vector2i, cells: &HashSet<Vector2i>) -> u8 {
+    // a bit is set for every neighbour of the same family
+    [
+        Neighbour::N, Neighbour::NE, Neighbour::E, Neighbour::SE,
+        Neighbour::S, Neighbour::SW, Neighbour::W, Neighbour::NW
+    ].iter()
+        .filter(|a| cells.contains(&(v + a.offset())))
+        .fold(0, |mask, a| mask | a.bit())
+}
@@ -0,0 +1,143 @@
+use rogalik_engine::{Color, GraphicsContext, Params2d};
+use rogalik_math::vectors::Vector2f;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{Context_, State};
+use crate::globals::{BACKGROUND_Z, BOARD_HEIGHT, BOARD_WIDTH, FOREGROUND_Z};
+use crate::sprite::StaticSprite;
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct Layer {
+    #[serde(with = "crate::save::atlas")]
+    atlas: &'static str,
+    // picked by the column and row, and advanced by the animation
+    indices: Vec<usize>,
+    color: (u8, u8, u8, u8),
+    size: (f32, f32),
+    // distance between the columns, the layer repeats horizontally
+    spacing: f32,
+    // bottom row position and the row count
+    y: f32,
+    rows: u32,
+    // share of the camera movement the layer follows:
+    // 0 is fixed to the board, towards 1 is farther away and
+    // below 0 is nearer than the board, drawn over the tiles
+    parallax: f32,
+    // horizontal drift in tiles per second
+    speed: f32,
+    // seconds per index step, 0 for no frame animation
+    frame_time: f32,
+    // vertical sway amplitude and frequency
+    sway: f32,
+    sway_speed: f32
+}
+impl Default for Layer {
+    fn default() -> Self {
+        Layer {
+            atlas: "tiles",
+            indices: vec![8],
+            color: (255, 255, 255, 255),
+            size: (1., 1.),
+            spacing: 1.,
+            y: 0.,
+            rows: 1,
+            parallax: 0.,
+            speed: 0.,
+            frame_time: 0.,
+            sway: 0.,
+            sway_speed: 0.
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+pub struct Backgrounds {
+    // used by levels without an entry
+    default: String,
+    levels: HashMap<String, String>,
+    // layers are listed from the farthest
+    sets: HashMap<String, Vec<Layer>>
+}
+
+pub fn load_backgrounds() -> Backgrounds {
+    ron::from_str(include_str!("../assets/backgrounds.ron")).expect("Invalid background data!")
+}
+
+impl Layer {
+    fn is_static(&self) -> bool {
+        // static layers are drawn from the prebuilt batch
+        self.parallax == 0. && self.speed == 0. && self.sway == 0.
+            && (self.frame_time == 0. || self.indices.len() < 2)
+    }
+    fn z_index(&self, idx: usize) -> i32 {
+        if self.parallax < 0. { return FOREGROUND_Z + idx as i32 }
+        BACKGROUND_Z + idx as i32
+    }
+    fn sprites(&self, z_index: i32, time: f32, offset: Vector2f) -> Vec<StaticSprite> {
+        if self.indices.len() == 0 || self.spacing <= 0. { return Vec::new() }
+        let columns = (BOARD_WIDTH as f32 / self.spacing).ceil() as usize + 2;
+        let span = columns as f32 * self.spacing;
+        let frame = if self.frame_time > 0. { (time / self.frame_time) as usize } else { 0 };
+        let (r, g, b, a) = self.color;
+
+        let mut sprites = Vec::new();
+        for col in 0..columns {
+            // wrapped, so a drifting layer never runs out of columns
+            let x = (col as f32 * self.spacing + self.speed * time).rem_euclid(span) - self.spacing;
+            let sway = self.sway * (self.sway_speed * time + col as f32).sin();
+            for row in 0..self.rows {
+                sprites.push(StaticSprite {
+                    atlas: self.atlas,
+                    index: self.indices[(col + row as usize + frame) % self.indices.len()],
+                    color: Color(r, g, b, a),
+                    position: Vector2f::new(x, self.y + row as f32 * self.size.1 + sway) + offset,
+                    z_index,
+                    size: Vector2f::new(self.size.0, self.size.1)
+                });
+            }
+        }
+        sprites
+    }
+}
+
+fn current_layers(state: &State) -> &[Layer] {
+    let backgrounds = &state.backgrounds;
+    let name = backgrounds.levels.get(state.level).unwrap_or(&backgrounds.default);
+    backgrounds.sets.get(name).map(|a| a.as_slice()).unwrap_or(&[])
+}
+
+pub fn static_sprites(state: &State) -> Vec<StaticSprite> {
+    current_layers(state).iter()
+        .enumerate()
+        .filter(|(_, a)| a.is_static())
+        .flat_map(|(i, a)| a.sprites(a.z_index(i), 0., Vector2f::new(0., 0.)))
+        .collect()
+}
+
+pub fn render_background(state: &State, context: &mut Context_) {
+    // only the moving layers, the rest is in the static batch.
+    // the camera only leaves the centre when shaking, so the depth shows as
+    // far layers shaking less than the board and near ones more
+    let centre = Vector2f::new(BOARD_WIDTH as f32 / 2., BOARD_HEIGHT as f32 / 2.);
+    let camera_offset = context.graphics.get_camera(state.camera_main)
+        .map(|a| a.get_target() - centre)
+        .unwrap_or(Vector2f::new(0., 0.));
+    // the animations follow the game clock, so they stop on pause
+    let time = state.level_time;
+
+    for (i, layer) in current_layers(state).iter().enumerate() {
+        if layer.is_static() { continue }
+        for sprite in layer.sprites(layer.z_index(i), time, layer.parallax * camera_offset) {
+            context.graphics.draw_atlas_sprite(
+                sprite.atlas,
+                sprite.index,
+                sprite.position,
+                sprite.z_index,
+                sprite.size,
+                Params2d { color: sprite.color, ..Default::default() }
+            );
+        }
+    }
+}
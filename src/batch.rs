@@ -0,0 +1,199 @@
+use rogalik_engine::{Color, GraphicsContext, Params2d};
+use rogalik_math::{aabb::Aabb, vectors::Vector2f};
+use std::collections::{HashMap, HashSet};
+
+use super::{Context_, State};
+use crate::sprite::{ATLASES, StaticSprite};
+
+// resolution of the prerendered layers, same as the tile art
+const PIXELS_PER_TILE: f32 = 16.;
+
+enum Layer {
+    // prerendered texture, loaded as a single sprite atlas
+    Texture { atlas: String, z_index: i32, bounds: Aabb },
+    // drawn sprite by sprite, when the texture could not be made
+    Sprites(Vec<StaticSprite>)
+}
+
+#[derive(Default)]
+pub struct StaticBatch {
+    layers: Vec<Layer>,
+    // a level's static sprites never change, so every texture is made once
+    loaded: HashSet<String>
+}
+
+struct Sheet {
+    width: usize,
+    columns: usize,
+    cell: (usize, usize),
+    // rgba, rows from the top
+    pixels: Vec<u8>
+}
+
+pub fn build_static_batch(state: &mut State, context: &mut Context_) {
+    // the board and the static backgrounds are drawn into a texture per depth,
+    // so each of them takes a single draw call
+    let sprites = state.board.sprites.iter()
+        .cloned()
+        .chain(crate::background::static_sprites(state))
+        .collect::<Vec<_>>();
+    let mut depths = sprites.iter().map(|a| a.z_index).collect::<Vec<_>>();
+    depths.sort();
+    depths.dedup();
+
+    let mut loaded = std::mem::take(&mut state.static_batch.loaded);
+    let mut sheets = HashMap::new();
+    let mut layers = Vec::new();
+    for z_index in depths {
+        let group = sprites.iter().filter(|a| a.z_index == z_index).collect::<Vec<_>>();
+        let Some(bounds) = group_bounds(&group) else { continue };
+        let atlas = format!("static_{}_{}", state.level, z_index);
+        if !loaded.contains(&atlas) {
+            let Some(data) = rasterize(&group, &bounds, &mut sheets) else {
+                eprintln!("Can't prerender {}, drawing its sprites one by one", atlas);
+                layers.push(Layer::Sprites(group.into_iter().cloned().collect()));
+                continue
+            };
+            context.graphics.load_sprite_atlas(&atlas, &data, 1, 1, None);
+            loaded.insert(atlas.clone());
+        }
+        layers.push(Layer::Texture { atlas, z_index, bounds });
+    }
+    state.static_batch = StaticBatch { layers, loaded };
+}
+
+pub fn render_static_batch(state: &State, context: &mut Context_) {
+    let Some(view) = view_bounds(state, context) else { return };
+    for layer in state.static_batch.layers.iter() {
+        match layer {
+            Layer::Texture { atlas, z_index, bounds } => {
+                if !bounds.intersects(&view) { continue }
+                context.graphics.draw_atlas_sprite(
+                    atlas,
+                    0,
+                    bounds.a,
+                    *z_index,
+                    bounds.b - bounds.a,
+                    Params2d::default()
+                );
+            },
+            Layer::Sprites(sprites) => {
+                for sprite in sprites.iter() {
+                    let bounds = Aabb::new(sprite.position, sprite.position + sprite.size);
+                    if !bounds.intersects(&view) { continue }
+                    context.graphics.draw_atlas_sprite(
+                        sprite.atlas,
+                        sprite.index,
+                        sprite.position,
+                        sprite.z_index,
+                        sprite.size,
+                        Params2d { color: sprite.color, ..Default::default() }
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn group_bounds(sprites: &[&StaticSprite]) -> Option<Aabb> {
+    // snapped to whole pixels
+    let first = sprites.first()?;
+    let (mut a, mut b) = (first.position, first.position + first.size);
+    for sprite in sprites.iter() {
+        a = Vector2f::new(a.x.min(sprite.position.x), a.y.min(sprite.position.y));
+        let end = sprite.position + sprite.size;
+        b = Vector2f::new(b.x.max(end.x), b.y.max(end.y));
+    }
+    let snap = |v: f32, f: fn(f32) -> f32| f(v * PIXELS_PER_TILE) / PIXELS_PER_TILE;
+    Some(Aabb::new(
+        Vector2f::new(snap(a.x, f32::floor), snap(a.y, f32::floor)),
+        Vector2f::new(snap(b.x, f32::ceil), snap(b.y, f32::ceil))
+    ))
+}
+
+fn rasterize(
+    sprites: &[&StaticSprite],
+    bounds: &Aabb,
+    sheets: &mut HashMap<&'static str, Sheet>
+) -> Option<Vec<u8>> {
+    // png encoded, the sprites are drawn in order with nearest sampling
+    let width = ((bounds.b.x - bounds.a.x) * PIXELS_PER_TILE).round() as usize;
+    let height = ((bounds.b.y - bounds.a.y) * PIXELS_PER_TILE).round() as usize;
+    let mut canvas = vec![0; 4 * width * height];
+
+    for sprite in sprites {
+        if !sheets.contains_key(sprite.atlas) {
+            sheets.insert(sprite.atlas, load_sheet(sprite.atlas)?);
+        }
+        let sheet = &sheets[sprite.atlas];
+        let cell_x = (sprite.index % sheet.columns) * sheet.cell.0;
+        let cell_y = (sprite.index / sheet.columns) * sheet.cell.1;
+        // image rows go down from the top of the bounds
+        let x0 = ((sprite.position.x - bounds.a.x) * PIXELS_PER_TILE).round() as usize;
+        let y0 = ((bounds.b.y - sprite.position.y - sprite.size.y) * PIXELS_PER_TILE).round() as usize;
+        let w = (sprite.size.x * PIXELS_PER_TILE).round() as usize;
+        let h = (sprite.size.y * PIXELS_PER_TILE).round() as usize;
+
+        for y in 0..h.min(height.saturating_sub(y0)) {
+            for x in 0..w.min(width.saturating_sub(x0)) {
+                let sx = cell_x + x * sheet.cell.0 / w;
+                let sy = cell_y + y * sheet.cell.1 / h;
+                let src = 4 * (sy * sheet.width + sx);
+                let Some(pixel) = sheet.pixels.get(src..src + 4) else { continue };
+                let dst = 4 * ((y0 + y) * width + x0 + x);
+                blend(&mut canvas[dst..dst + 4], pixel, sprite.color);
+            }
+        }
+    }
+    encode(width as u32, height as u32, &canvas)
+}
+
+fn blend(dst: &mut [u8], src: &[u8], tint: Color) {
+    // the tint multiplies like in the sprite shader, then alpha over
+    let tint = [tint.0, tint.1, tint.2, tint.3];
+    let value = |i: usize| src[i] as f32 * tint[i] as f32 / (255. * 255.);
+    let src_a = value(3);
+    if src_a <= 0. { return }
+    let dst_a = dst[3] as f32 / 255.;
+    let out_a = src_a + dst_a * (1. - src_a);
+    for i in 0..3 {
+        let d = dst[i] as f32 / 255.;
+        let out = (value(i) * src_a + d * dst_a * (1. - src_a)) / out_a;
+        dst[i] = (out * 255.).round() as u8;
+    }
+    dst[3] = (out_a * 255.).round() as u8;
+}
+
+fn load_sheet(name: &str) -> Option<Sheet> {
+    let (_, data, grid) = ATLASES.iter().find(|a| a.0 == name)?;
+    let mut reader = png::Decoder::new(*data).read_info().ok()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).ok()?;
+    // the bundled atlases are all 8 bit rgba
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return None
+    }
+    Some(Sheet {
+        width: info.width as usize,
+        columns: *grid,
+        cell: (info.width as usize / grid, info.height as usize / grid),
+        pixels
+    })
+}
+
+fn encode(width: u32, height: u32, pixels: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(pixels).ok()?;
+    writer.finish().ok()?;
+    Some(data)
+}
+
+fn view_bounds(state: &State, context: &Context_) -> Option<Aabb> {
+    let centre = context.graphics.get_camera(state.camera_main)?.get_target();
+    let half = 0.5 * crate::ui::view_size(state, context);
+    Some(Aabb::new(centre - half, centre + half))
+}
@@ -0,0 +1,235 @@
+use rogalik_engine::input::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+
+use super::Context_;
+use crate::storage;
+
+const BINDINGS_KEY: &str = "bindings";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Lift,
+    Left,
+    Right,
+    Confirm,
+    Pause
+}
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Lift => "Lift",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Confirm => "Confirm",
+            Action::Pause => "Pause"
+        }
+    }
+    fn is_shared(&self) -> bool {
+        // not bound per player
+        matches!(self, Action::Confirm | Action::Pause)
+    }
+}
+
+// every rebindable (player, action) pair, in the settings screen order
+pub const SLOTS: [(usize, Action); 8] = [
+    (0, Action::Lift),
+    (0, Action::Left),
+    (0, Action::Right),
+    (1, Action::Lift),
+    (1, Action::Left),
+    (1, Action::Right),
+    (0, Action::Confirm),
+    (0, Action::Pause)
+];
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct PlayerKeys {
+    #[serde(with = "key_name")]
+    pub lift: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub left: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub right: VirtualKeyCode
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Bindings {
+    pub players: [PlayerKeys; 2],
+    #[serde(with = "key_name")]
+    pub confirm: VirtualKeyCode,
+    #[serde(with = "key_name")]
+    pub pause: VirtualKeyCode
+}
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            players: [
+                PlayerKeys {
+                    lift: VirtualKeyCode::W,
+                    left: VirtualKeyCode::A,
+                    right: VirtualKeyCode::D
+                },
+                PlayerKeys {
+                    lift: VirtualKeyCode::Up,
+                    left: VirtualKeyCode::Left,
+                    right: VirtualKeyCode::Right
+                }
+            ],
+            confirm: VirtualKeyCode::Space,
+            pause: VirtualKeyCode::Escape
+        }
+    }
+}
+impl Bindings {
+    pub fn key(&self, player: usize, action: Action) -> VirtualKeyCode {
+        match action {
+            Action::Lift => self.players[player].lift,
+            Action::Left => self.players[player].left,
+            Action::Right => self.players[player].right,
+            Action::Confirm => self.confirm,
+            Action::Pause => self.pause
+        }
+    }
+    fn key_mut(&mut self, player: usize, action: Action) -> &mut VirtualKeyCode {
+        match action {
+            Action::Lift => &mut self.players[player].lift,
+            Action::Left => &mut self.players[player].left,
+            Action::Right => &mut self.players[player].right,
+            Action::Confirm => &mut self.confirm,
+            Action::Pause => &mut self.pause
+        }
+    }
+    pub fn rebind(&mut self, player: usize, action: Action, key: VirtualKeyCode) {
+        // a key already in use is swapped, so no action is ever left unbound
+        let old = self.key(player, action);
+        for (p, a) in SLOTS {
+            if self.key(p, a) == key {
+                *self.key_mut(p, a) = old;
+            }
+        }
+        *self.key_mut(player, action) = key;
+    }
+    pub fn is_bound(&self, key: VirtualKeyCode) -> bool {
+        SLOTS.iter().any(|(p, a)| self.key(*p, *a) == key)
+    }
+    pub fn is_pressed(&self, action: Action, context: &Context_) -> bool {
+        // shared actions only
+        if !action.is_shared() { return false }
+        context.input.is_key_pressed(self.key(0, action))
+    }
+}
+
+pub fn load_bindings() -> Bindings {
+    storage::load(BINDINGS_KEY).unwrap_or_default()
+}
+
+pub fn save_bindings(bindings: &Bindings) {
+    storage::save(BINDINGS_KEY, bindings);
+}
+
+pub fn slot_name(player: usize, action: Action) -> String {
+    match action.is_shared() {
+        true => action.name().to_string(),
+        false => format!("P{} {}", player + 1, action.name())
+    }
+}
+
+pub fn slot_text(player: usize, action: Action, bindings: &Bindings) -> String {
+    format!("{}: {}", slot_name(player, action), key_label(bindings.key(player, action)))
+}
+
+pub fn key_label(key: VirtualKeyCode) -> &'static str {
+    KEY_NAMES.iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, name)| *name)
+        .unwrap_or("?")
+}
+
+pub fn pressed_key(context: &Context_) -> Option<VirtualKeyCode> {
+    // used when capturing a new binding
+    KEY_NAMES.iter()
+        .map(|(k, _)| *k)
+        .find(|k| context.input.is_key_pressed(*k))
+}
+
+mod key_name {
+    // keys are stored by their display names
+    use rogalik_engine::input::VirtualKeyCode;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &VirtualKeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(super::key_label(*key))
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<VirtualKeyCode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        super::KEY_NAMES.iter()
+            .find(|(_, a)| *a == name)
+            .map(|(k, _)| *k)
+            .ok_or_else(|| D::Error::custom(format!("Unknown key: {}", name)))
+    }
+}
+
+const KEY_NAMES: [(VirtualKeyCode, &str); 62] = [
+    (VirtualKeyCode::A, "A"),
+    (VirtualKeyCode::B, "B"),
+    (VirtualKeyCode::C, "C"),
+    (VirtualKeyCode::D, "D"),
+    (VirtualKeyCode::E, "E"),
+    (VirtualKeyCode::F, "F"),
+    (VirtualKeyCode::G, "G"),
+    (VirtualKeyCode::H, "H"),
+    (VirtualKeyCode::I, "I"),
+    (VirtualKeyCode::J, "J"),
+    (VirtualKeyCode::K, "K"),
+    (VirtualKeyCode::L, "L"),
+    (VirtualKeyCode::M, "M"),
+    (VirtualKeyCode::N, "N"),
+    (VirtualKeyCode::O, "O"),
+    (VirtualKeyCode::P, "P"),
+    (VirtualKeyCode::Q, "Q"),
+    (VirtualKeyCode::R, "R"),
+    (VirtualKeyCode::S, "S"),
+    (VirtualKeyCode::T, "T"),
+    (VirtualKeyCode::U, "U"),
+    (VirtualKeyCode::V, "V"),
+    (VirtualKeyCode::W, "W"),
+    (VirtualKeyCode::X, "X"),
+    (VirtualKeyCode::Y, "Y"),
+    (VirtualKeyCode::Z, "Z"),
+    (VirtualKeyCode::Key0, "0"),
+    (VirtualKeyCode::Key1, "1"),
+    (VirtualKeyCode::Key2, "2"),
+    (VirtualKeyCode::Key3, "3"),
+    (VirtualKeyCode::Key4, "4"),
+    (VirtualKeyCode::Key5, "5"),
+    (VirtualKeyCode::Key6, "6"),
+    (VirtualKeyCode::Key7, "7"),
+    (VirtualKeyCode::Key8, "8"),
+    (VirtualKeyCode::Key9, "9"),
+    (VirtualKeyCode::Up, "Up"),
+    (VirtualKeyCode::Down, "Down"),
+    (VirtualKeyCode::Left, "Left"),
+    (VirtualKeyCode::Right, "Right"),
+    (VirtualKeyCode::Space, "Space"),
+    (VirtualKeyCode::Return, "Enter"),
+    (VirtualKeyCode::Escape, "Escape"),
+    (VirtualKeyCode::Tab, "Tab"),
+    (VirtualKeyCode::Back, "Backspace"),
+    (VirtualKeyCode::LShift, "LShift"),
+    (VirtualKeyCode::RShift, "RShift"),
+    (VirtualKeyCode::LControl, "LCtrl"),
+    (VirtualKeyCode::RControl, "RCtrl"),
+    (VirtualKeyCode::LAlt, "LAlt"),
+    (VirtualKeyCode::RAlt, "RAlt"),
+    (VirtualKeyCode::Comma, ","),
+    (VirtualKeyCode::Period, "."),
+    (VirtualKeyCode::Slash, "/"),
+    (VirtualKeyCode::Semicolon, ";"),
+    (VirtualKeyCode::Apostrophe, "'"),
+    (VirtualKeyCode::LBracket, "["),
+    (VirtualKeyCode::RBracket, "]"),
+    (VirtualKeyCode::Minus, "-"),
+    (VirtualKeyCode::Equals, "="),
+    (VirtualKeyCode::Home, "Home"),
+    (VirtualKeyCode::End, "End")
+];
@@ -3,8 +3,10 @@ use rogalik_math::{
     vectors::{Vector2f, Vector2i}
 };
 use rogalik_engine::Color;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
+use crate::autotile::{Family, Tileset};
 use crate::creatures::{Creature, CreatureKind};
 use crate::globals::{TILE_SIZE, BOARD_WIDTH, BOARD_HEIGHT, MAMMOTH_BLOW_V_OFFSET, MAMMOTH_BLOW_V_SIZE};
 use crate::sprite::StaticSprite;
@@ -16,7 +18,9 @@ pub struct Board {
     pub gates: Vec<Gate>
 }
 
+#[derive(Deserialize, Serialize)]
 pub struct Gate {
+    #[serde(with = "crate::save::Vector2fDef")]
     pub position: Vector2f,
     pub has_passenger: bool,
     pub since_pickup: f32
@@ -28,20 +32,26 @@ impl Gate {
     }
 }
 
-pub fn generate_board(data: &str) -> (Board, Vec<Creature>)
+pub fn generate_board(data: &str, tileset: &Tileset) -> (Board, Vec<Creature>)
 {
     let locations = parse_str_data(data);
     let mut sprites = Vec::new();
     let mut colliders = Vec::new();
-    
-    for r in locations["rocks"].iter() {
-        let (sprite, aabb) = get_rock(r.0, &locations["rocks"].iter().map(|a| a.0).collect());
-        sprites.push(sprite);
-        colliders.push(aabb);
+
+    for family in tileset.families.iter() {
+        let cells = parse_tiles(data, family.symbol);
+        for v in cells.iter() {
+            let (sprite, aabb) = get_tile(*v, family, &cells, tileset);
+            sprites.push(sprite);
+            colliders.push(aabb);
+        }
     }
+    // gates are numbered in reading order, so level scripts can refer to them
+    let mut gate_positions = locations["gates"].iter().map(|a| a.0).collect::<Vec<_>>();
+    gate_positions.sort_by_key(|v| (-v.y, v.x));
     let mut gates = Vec::new();
-    for (i, g) in locations["gates"].iter().enumerate() {
-        let (sprite, gate) = get_gate(g.0.as_f32(), i as u32);
+    for (i, g) in gate_positions.iter().enumerate() {
+        let (sprite, gate) = get_gate(g.as_f32(), i as u32);
         sprites.push(sprite);
         gates.push(gate);
     }
@@ -53,20 +63,27 @@ pub fn generate_board(data: &str) -> (Board, Vec<Creature>)
     for position in locations["mammoths"].iter() {
         creatures.push(get_mammoth(position.0.as_f32(), position.1));
     }
+    // a stable order keeps networked simulations in sync
+    creatures.sort_by(|a, b| {
+        a.sprite.position.y.total_cmp(&b.sprite.position.y)
+            .then(a.sprite.position.x.total_cmp(&b.sprite.position.x))
+    });
 
     (Board { sprites, colliders, gates }, creatures)
 }
 
 
-fn get_rock(v: Vector2i, other: &HashSet<Vector2i>) -> (StaticSprite, Aabb) {
+fn get_tile(
+    v: Vector2i,
+    family: &Family,
+    cells: &HashSet<Vector2i>,
+    tileset: &Tileset
+) -> (StaticSprite, Aabb) {
     let position = v.as_f32() * TILE_SIZE;
-    let mut offset = 0;
-    if !other.contains(&(v + Vector2i::UP)) { offset += 1 };
-    if !other.contains(&(v + Vector2i::DOWN)) { offset += 2 };
     let sprite = StaticSprite {
-        atlas: "tiles",
-        index: offset,
-        color: Color(255, 255, 255, 255),
+        atlas: family.atlas,
+        index: tileset.sprite_index(family, v, cells),
+        color: family.color(),
         size: Vector2f::new(TILE_SIZE, TILE_SIZE),
         position,
         z_index: crate::globals::TILE_Z
@@ -120,10 +137,18 @@ fn get_mammoth(position: Vector2f, flip: bool) -> Creature {
     )
 }
 
-fn parse_str_data(data: &str) -> HashMap<&str, HashSet<(Vector2i, bool)>> {
+pub fn split_level_data(data: &str) -> (&str, &str) {
+    // board rows are optionally followed by a passenger manifest,
+    // separated by a `---` line
+    match data.split_once("\n---") {
+        Some((board, manifest)) => (board, manifest),
+        None => (data, "")
+    }
+}
+
+fn board_lines(data: &str) -> Vec<String> {
     // the data should not have multibyte characters
     // so it's safe byte len = char len
-    // returns (position, flip)
     let lines = data.split('\n')
         .map(|s| match s.len() {
             a if a > BOARD_WIDTH as usize => s[..BOARD_WIDTH as usize].to_string(),
@@ -131,9 +156,28 @@ fn parse_str_data(data: &str) -> HashMap<&str, HashSet<(Vector2i, bool)>> {
         })
         .collect::<Vec<_>>();
     if lines.len() != 10 { panic!("Incorrect level data: row count mismatch!")};
+    lines
+}
+
+fn parse_tiles(data: &str, symbol: char) -> HashSet<Vector2i> {
+    // positions of a single tile family
+    let mut cells = HashSet::new();
+    for (row, line) in board_lines(data).iter().enumerate() {
+        let y = BOARD_HEIGHT - row as u32 - 1;
+        for (col, c) in line.chars().enumerate() {
+            if c == symbol {
+                cells.insert(Vector2i::new(col as i32, y as i32));
+            }
+        }
+    }
+    cells
+}
+
+fn parse_str_data(data: &str) -> HashMap<&str, HashSet<(Vector2i, bool)>> {
+    // returns (position, flip)
+    let lines = board_lines(data);
 
     let mut locations = HashMap::from_iter(vec![
-        ("rocks", HashSet::new()),
         ("gates", HashSet::new()),
         ("birds", HashSet::new()),
         ("mammoths", HashSet::new()),
@@ -144,7 +188,6 @@ fn parse_str_data(data: &str) -> HashMap<&str, HashSet<(Vector2i, bool)>> {
         for (col, c) in line.chars().enumerate() {
             let v = Vector2i::new(col as i32, y as i32);
             match c {
-                '#' => { locations.get_mut("rocks").unwrap().insert((v, false)); },
                 'G' => { locations.get_mut("gates").unwrap().insert((v, false)); },
                 'B' => { locations.get_mut("birds").unwrap().insert((v, false)); },
                 'b' => { locations.get_mut("birds").unwrap().insert((v, true)); },
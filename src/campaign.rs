@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use super::State;
+use crate::storage;
+
+const PROGRESS_KEY: &str = "campaign";
+const MAX_STARS: u32 = 3;
+
+#[derive(Deserialize)]
+pub enum Objective {
+    // deliver the given number of passengers
+    Deliver(u32),
+    // complete within the given number of seconds
+    TimeLimit(f32),
+    NoReputationLoss
+}
+
+#[derive(Deserialize)]
+pub struct CampaignLevel {
+    pub level: String,
+    pub goals: Vec<Objective>,
+    pub stars: Vec<Objective>
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct Progress {
+    // best rating per campaign level, 0 when not completed yet
+    pub stars: Vec<u32>
+}
+
+pub enum ObjectiveStatus {
+    InProgress,
+    Complete(u32),
+    Failed
+}
+
+#[derive(Default)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+    pub progress: Progress,
+    pub last_stars: u32
+}
+impl Campaign {
+    pub fn stars(&self, idx: usize) -> u32 {
+        self.progress.stars.get(idx).copied().unwrap_or(0)
+    }
+    pub fn is_unlocked(&self, idx: usize) -> bool {
+        idx == 0 || self.stars(idx - 1) > 0
+    }
+    pub fn complete_level(&mut self, idx: usize, stars: u32) {
+        if self.progress.stars.len() <= idx {
+            self.progress.stars.resize(idx + 1, 0);
+        }
+        self.progress.stars[idx] = self.progress.stars[idx].max(stars);
+        self.last_stars = stars;
+        storage::save(PROGRESS_KEY, &self.progress);
+    }
+}
+
+pub fn load_campaign(data: &str) -> Campaign {
+    let levels = ron::from_str(data).expect("Incorrect campaign data!");
+    let progress = storage::load(PROGRESS_KEY).unwrap_or_default();
+    Campaign { levels, progress, last_stars: 0 }
+}
+
+pub fn check_objectives(state: &State, idx: usize) -> ObjectiveStatus {
+    let Some(level) = state.campaign.levels.get(idx) else { return ObjectiveStatus::InProgress };
+    if level.goals.iter().any(|a| is_failed(a, state)) {
+        return ObjectiveStatus::Failed
+    }
+    if !level.goals.iter().all(|a| is_met(a, state)) {
+        return ObjectiveStatus::InProgress
+    }
+    let bonus = level.stars.iter().filter(|a| is_met(a, state)).count() as u32;
+    ObjectiveStatus::Complete(MAX_STARS.min(1 + bonus))
+}
+
+pub fn objective_text(level: &CampaignLevel, state: &State) -> String {
+    level.goals.iter()
+        .map(|a| match a {
+            Objective::Deliver(count) => format!(
+                "{}/{}", state.score().min(*count), count
+            ),
+            Objective::TimeLimit(limit) => format!(
+                "{}s", (limit - state.level_time).max(0.).ceil()
+            ),
+            Objective::NoReputationLoss => "no misses".to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn is_met(objective: &Objective, state: &State) -> bool {
+    match objective {
+        Objective::Deliver(count) => state.score() >= *count,
+        Objective::TimeLimit(limit) => state.level_time <= *limit,
+        Objective::NoReputationLoss => state.reputation >= state.config.reputation
+    }
+}
+
+fn is_failed(objective: &Objective, state: &State) -> bool {
+    // delivery goals can still be met until another goal fails
+    match objective {
+        Objective::Deliver(_) => false,
+        _ => !is_met(objective, state)
+    }
+}
@@ -0,0 +1,310 @@
+use rogalik_engine::input::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{Context_, GameMode, State};
+
+// the balance values can be edited without rebuilding the game:
+// native builds read this file from the working directory first
+#[cfg(not(target_arch="wasm32"))]
+const CONFIG_PATH: &str = "config.ron";
+
+// every tunable field with its debug panel step
+// (reputation and spawn_drop_every are whole numbers)
+pub const FIELDS: [(&str, f32); 21] = [
+    ("reputation", 1.),
+    ("stamina_use", 0.01),
+    ("stamina_recovery", 0.01),
+    ("spawn_interval", 0.5),
+    ("spawn_drop_every", 1.),
+    ("passenger_max_wait", 0.5),
+    ("passenger_load_dist", 0.25),
+    ("passenger_walk_speed", 0.25),
+    ("passenger_fall_speed", 0.25),
+    ("knock_down_speed", 0.25),
+    ("fly_acc", 0.25),
+    ("fly_max_speed", 0.25),
+    ("hor_drag", 0.25),
+    ("lift_acc", 0.25),
+    ("lift_max_speed", 0.25),
+    ("gravity_acc", 0.25),
+    ("damage_speed", 0.25),
+    ("bump_speed", 0.25),
+    ("bird_speed", 0.25),
+    ("hit_immunity", 0.25),
+    ("mammoth_blow_speed", 0.25)
+];
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub reputation: u32,
+    pub stamina_use: f32,
+    pub stamina_recovery: f32,
+    pub spawn_interval: f32,
+    // deliveries needed to shorten the spawn interval by a second
+    pub spawn_drop_every: u32,
+    pub passenger_max_wait: f32,
+    pub passenger_load_dist: f32,
+    pub passenger_walk_speed: f32,
+    pub passenger_fall_speed: f32,
+    pub knock_down_speed: f32,
+    pub fly_acc: f32,
+    pub fly_max_speed: f32,
+    pub hor_drag: f32,
+    pub lift_acc: f32,
+    pub lift_max_speed: f32,
+    pub gravity_acc: f32,
+    pub damage_speed: f32,
+    pub bump_speed: f32,
+    pub bird_speed: f32,
+    pub hit_immunity: f32,
+    pub mammoth_blow_speed: f32
+}
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            reputation: 5,
+            stamina_use: 0.1,
+            stamina_recovery: 0.04,
+            spawn_interval: 8.,
+            spawn_drop_every: 10,
+            passenger_max_wait: 12.5,
+            passenger_load_dist: 3.,
+            passenger_walk_speed: 2.,
+            passenger_fall_speed: 5.,
+            knock_down_speed: 2.5,
+            fly_acc: 6.,
+            fly_max_speed: 4.,
+            hor_drag: 4.,
+            lift_acc: 3.,
+            lift_max_speed: 4.,
+            gravity_acc: 5.,
+            damage_speed: 5.,
+            bump_speed: 4.,
+            bird_speed: 5.,
+            hit_immunity: 2.,
+            mammoth_blow_speed: 2.
+        }
+    }
+}
+impl GameConfig {
+    fn field_mut(&mut self, name: &str) -> Option<&mut f32> {
+        // the whole number fields are handled by get / set
+        match name {
+            "stamina_use" => Some(&mut self.stamina_use),
+            "stamina_recovery" => Some(&mut self.stamina_recovery),
+            "spawn_interval" => Some(&mut self.spawn_interval),
+            "passenger_max_wait" => Some(&mut self.passenger_max_wait),
+            "passenger_load_dist" => Some(&mut self.passenger_load_dist),
+            "passenger_walk_speed" => Some(&mut self.passenger_walk_speed),
+            "passenger_fall_speed" => Some(&mut self.passenger_fall_speed),
+            "knock_down_speed" => Some(&mut self.knock_down_speed),
+            "fly_acc" => Some(&mut self.fly_acc),
+            "fly_max_speed" => Some(&mut self.fly_max_speed),
+            "hor_drag" => Some(&mut self.hor_drag),
+            "lift_acc" => Some(&mut self.lift_acc),
+            "lift_max_speed" => Some(&mut self.lift_max_speed),
+            "gravity_acc" => Some(&mut self.gravity_acc),
+            "damage_speed" => Some(&mut self.damage_speed),
+            "bump_speed" => Some(&mut self.bump_speed),
+            "bird_speed" => Some(&mut self.bird_speed),
+            "hit_immunity" => Some(&mut self.hit_immunity),
+            "mammoth_blow_speed" => Some(&mut self.mammoth_blow_speed),
+            _ => None
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<f32> {
+        let value = match name {
+            "reputation" => self.reputation as f32,
+            "stamina_use" => self.stamina_use,
+            "stamina_recovery" => self.stamina_recovery,
+            "spawn_interval" => self.spawn_interval,
+            "spawn_drop_every" => self.spawn_drop_every as f32,
+            "passenger_max_wait" => self.passenger_max_wait,
+            "passenger_load_dist" => self.passenger_load_dist,
+            "passenger_walk_speed" => self.passenger_walk_speed,
+            "passenger_fall_speed" => self.passenger_fall_speed,
+            "knock_down_speed" => self.knock_down_speed,
+            "fly_acc" => self.fly_acc,
+            "fly_max_speed" => self.fly_max_speed,
+            "hor_drag" => self.hor_drag,
+            "lift_acc" => self.lift_acc,
+            "lift_max_speed" => self.lift_max_speed,
+            "gravity_acc" => self.gravity_acc,
+            "damage_speed" => self.damage_speed,
+            "bump_speed" => self.bump_speed,
+            "bird_speed" => self.bird_speed,
+            "hit_immunity" => self.hit_immunity,
+            "mammoth_blow_speed" => self.mammoth_blow_speed,
+            _ => return None
+        };
+        Some(value)
+    }
+    pub fn set(&mut self, name: &str, value: f32) {
+        // negative values make no sense for any of the fields
+        let value = value.max(0.);
+        match name {
+            "reputation" => self.reputation = (value.round() as u32).max(1),
+            "spawn_drop_every" => self.spawn_drop_every = (value.round() as u32).max(1),
+            _ => if let Some(field) = self.field_mut(name) { *field = value }
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub base: GameConfig,
+    // field overrides by level name, eg. "Birdy": { "bird_speed": 4.0 }
+    pub levels: HashMap<String, HashMap<String, f32>>
+}
+impl ConfigFile {
+    pub fn for_level(&self, level: &str) -> GameConfig {
+        let mut config = self.base.clone();
+        let Some(overrides) = self.levels.get(level) else { return config };
+        for (name, value) in overrides.iter() {
+            config.set(name, *value);
+        }
+        config
+    }
+}
+
+pub fn load_config() -> ConfigFile {
+    #[cfg(not(target_arch="wasm32"))]
+    if let Ok(data) = std::fs::read_to_string(CONFIG_PATH) {
+        match ron::from_str(&data) {
+            Ok(file) => return file,
+            Err(e) => eprintln!("Can't parse {}, using the bundled config: {}", CONFIG_PATH, e)
+        }
+    }
+    embedded_config()
+}
+
+fn embedded_config() -> ConfigFile {
+    ron::from_str(include_str!("../assets/config.ron")).expect("Invalid config data!")
+}
+
+pub fn level_config(state: &State) -> GameConfig {
+    // lockstep peers only share their inputs, so online games ignore
+    // the local file, which could differ between them
+    if state.mode == GameMode::Online {
+        return embedded_config().for_level(state.level)
+    }
+    state.config_file.for_level(state.level)
+}
+
+#[derive(Default)]
+pub struct DebugPanel {
+    pub visible: bool,
+    pub selected: usize,
+    // total change of every edited field, the live config also has
+    // the level overrides and the difficulty applied, so it is not saved as a whole
+    pub edits: HashMap<&'static str, f32>
+}
+
+fn is_available(state: &State) -> bool {
+    // development builds only, and never in ranked or shared seed modes
+    cfg!(debug_assertions)
+        && matches!(state.mode, GameMode::FreePlay | GameMode::Campaign(_) | GameMode::CoOp)
+}
+
+pub fn update_debug_panel(state: &mut State, context: &Context_) {
+    // F1 toggles, page up / down selects, - and = adjust the live value
+    if !is_available(state) {
+        state.debug.visible = false;
+        return
+    }
+    if context.input.is_key_pressed(VirtualKeyCode::F1) {
+        state.debug.visible = !state.debug.visible;
+    }
+    if !state.debug.visible { return }
+    let panel = &mut state.debug;
+    if context.input.is_key_pressed(VirtualKeyCode::PageDown) {
+        panel.selected = (panel.selected + 1) % FIELDS.len();
+    }
+    if context.input.is_key_pressed(VirtualKeyCode::PageUp) {
+        panel.selected = (panel.selected + FIELDS.len() - 1) % FIELDS.len();
+    }
+    let (name, step) = FIELDS[panel.selected];
+    let change = match (
+        context.input.is_key_pressed(VirtualKeyCode::Minus),
+        context.input.is_key_pressed(VirtualKeyCode::Equals)
+    ) {
+        (true, false) => -step,
+        (false, true) => step,
+        _ => 0.
+    };
+    if change != 0. {
+        let Some(value) = state.config.get(name) else { return };
+        state.config.set(name, value + change);
+        *panel.edits.entry(name).or_insert(0.) += change;
+    }
+    // F2 saves the edits as overrides of the current level, F3 into the base
+    #[cfg(not(target_arch="wasm32"))]
+    if context.input.is_key_pressed(VirtualKeyCode::F2) {
+        let level = state.level;
+        save_edits(state, Some(level));
+    }
+    #[cfg(not(target_arch="wasm32"))]
+    if context.input.is_key_pressed(VirtualKeyCode::F3) {
+        save_edits(state, None);
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+fn save_edits(state: &mut State, level: Option<&str>) {
+    if state.debug.edits.is_empty() { return }
+    let edits = std::mem::take(&mut state.debug.edits);
+    let file = &mut state.config_file;
+    match level {
+        Some(level) => {
+            // applied on top of the base and any previous override
+            let unedited = file.for_level(level);
+            let overrides = file.levels.entry(level.to_string()).or_default();
+            for (name, change) in edits {
+                let Some(value) = unedited.get(name) else { continue };
+                overrides.insert(name.to_string(), value + change);
+            }
+        },
+        None => {
+            for (name, change) in edits {
+                let Some(value) = file.base.get(name) else { continue };
+                file.base.set(name, value + change);
+            }
+        }
+    }
+    let options = ron::ser::PrettyConfig::default();
+    if let Ok(data) = ron::ser::to_string_pretty(file, options) {
+        let _ = std::fs::write(CONFIG_PATH, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_cover_the_whole_config() {
+        // a field missing here would be left out of the debug panel
+        let data = ron::to_string(&GameConfig::default()).unwrap();
+        let ron::Value::Map(map) = ron::from_str(&data).unwrap() else {
+            panic!("The config is not serialized as a map")
+        };
+        assert_eq!(FIELDS.len(), map.len());
+        for (key, _) in map.iter() {
+            let ron::Value::String(key) = key else { panic!("Unexpected key: {:?}", key) };
+            assert!(FIELDS.iter().any(|(name, _)| name == key), "Missing field: {}", key);
+        }
+    }
+
+    #[test]
+    fn fields_round_trip() {
+        let mut config = GameConfig::default();
+        for (name, step) in FIELDS {
+            let value = config.get(name).unwrap_or_else(|| panic!("No getter for {}", name));
+            config.set(name, value + step);
+            assert_eq!(config.get(name), Some(value + step), "Setter of {}", name);
+        }
+    }
+}
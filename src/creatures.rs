@@ -4,8 +4,12 @@ use rogalik_math::{
     vectors::{Vector2f, Vector2i}
 };
 
+use serde::{Deserialize, Serialize};
+
+use crate::audio::SoundEvent;
+use crate::config::GameConfig;
 use crate::globals::{
-    TOLERANCE, BIRD_SPEED, BIRD_MARGIN, BOARD_WIDTH, TILE_SIZE, HIT_IMMUNITY, MAMMOTH_BLOW_SPEED
+    TOLERANCE, BIRD_MARGIN, BOARD_WIDTH, TILE_SIZE
 };
 use crate::player::{Player, try_hit};
 use crate::sprite::DynamicSprite;
@@ -13,14 +17,17 @@ use crate::utils::almost_eq;
 
 use super::State;
 
+#[derive(Deserialize, Serialize)]
 pub enum CreatureKind {
     Bird,
     Mammoth
 }
 
+#[derive(Deserialize, Serialize)]
 pub struct Creature {
     pub sprite: DynamicSprite,
     pub kind: CreatureKind,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub dir: Vector2f
 }
 impl Creature {
@@ -53,15 +60,15 @@ impl Creature {
     }
 }
 
-pub fn update_creature(creature: &mut Creature, delta: f32) {
+pub fn update_creature(creature: &mut Creature, config: &GameConfig, delta: f32) {
     match creature.kind {
-        CreatureKind::Bird => fly_bird(creature, delta),
+        CreatureKind::Bird => fly_bird(creature, config, delta),
         CreatureKind::Mammoth => ()
     }
 }
 
-fn fly_bird(creature: &mut Creature, delta: f32) {
-    creature.sprite.position += creature.dir * BIRD_SPEED * delta;
+fn fly_bird(creature: &mut Creature, config: &GameConfig, delta: f32) {
+    creature.sprite.position += creature.dir * config.bird_speed * delta;
     if almost_eq(creature.dir.x, 1.) {
         if creature.sprite.position.x > (BOARD_WIDTH + BIRD_MARGIN) as f32 / TILE_SIZE {
             creature.sprite.position.x = -(BIRD_MARGIN as f32) / TILE_SIZE;
@@ -75,28 +82,37 @@ fn fly_bird(creature: &mut Creature, delta: f32) {
 
 pub fn check_interactions(state: &mut State) {
     for creature in state.creatures.iter() {
-        match creature.kind {
-            CreatureKind::Bird => {
-                if try_bird_collision(creature, &mut state.player) {
-                    if try_hit(&mut state.player) {
-                        state.audio.play("hit");
+        for player in state.players.iter_mut() {
+            match creature.kind {
+                CreatureKind::Bird => {
+                    if try_bird_collision(creature, player, &state.config) {
+                        if try_hit(player, &mut state.reputation, &state.config) {
+                            state.audio.emit_at(SoundEvent::Hit, player.sprite.centre().x);
+                            state.effects.hit();
+                            state.particles.burst("feathers", player.sprite.centre(), creature.dir.x);
+                        }
+                    }
+                },
+                CreatureKind::Mammoth => {
+                    if try_mammoth_blow(creature, player, &state.config) {
+                        state.audio.emit_at(SoundEvent::MammothBlow, creature.sprite.centre().x);
                     }
                 }
-            },
-            CreatureKind::Mammoth => try_mammoth_blow(creature, &mut state.player)
+            }
         }
     }
 }
 
-fn try_bird_collision(creature: &Creature, player: &mut Player) -> bool {
+fn try_bird_collision(creature: &Creature, player: &mut Player, config: &GameConfig) -> bool {
     if player.immunity > TOLERANCE { return false };
     if !player.sprite.aabb().intersects(&creature.sprite.aabb()) { return false }
-    player.v.x += 2.0 * creature.dir.x * BIRD_SPEED;
+    player.v.x += 2.0 * creature.dir.x * config.bird_speed;
     true
 }
 
-fn try_mammoth_blow(creature: &Creature, player: &mut Player) {
-    if !player.sprite.aabb().intersects(&creature.sprite.aabb()) { return }
+fn try_mammoth_blow(creature: &Creature, player: &mut Player, config: &GameConfig) -> bool {
+    if !player.sprite.aabb().intersects(&creature.sprite.aabb()) { return false }
     let v = Vector2f::new(creature.dir.x, 1.);
-    player.v += v * MAMMOTH_BLOW_SPEED;
+    player.v += v * config.mammoth_blow_speed;
+    true
 }
\ No newline at end of file
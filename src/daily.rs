@@ -0,0 +1,62 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use super::{GameMode, GameState, State};
+use crate::board::split_level_data;
+use crate::passenger::parse_manifest;
+use crate::storage;
+use crate::utils::{current_day, GameRng};
+
+const RESULTS_KEY: &str = "daily";
+
+#[derive(Deserialize, Serialize)]
+pub struct DailyResult {
+    pub day: u64,
+    pub level: String,
+    pub score: u32
+}
+
+#[derive(Default)]
+pub struct Daily {
+    pub results: Vec<DailyResult>
+}
+impl Daily {
+    pub fn today(&self) -> Option<&DailyResult> {
+        let day = current_day();
+        self.results.iter().find(|a| a.day == day)
+    }
+}
+
+pub fn load_results() -> Daily {
+    Daily { results: storage::load(RESULTS_KEY).unwrap_or_default() }
+}
+
+pub fn start(state: &mut State) {
+    if state.daily.today().is_some() { return }
+    let day = current_day();
+    let seed = GameRng::new(day).next_u64();
+
+    // only levels with random spawns, sorted so every player gets the same pick
+    let mut levels = state.level_data.iter()
+        .filter(|(_, data)| parse_manifest(split_level_data(data).1).random)
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    if levels.len() == 0 { return }
+    levels.sort();
+    let level = levels[(seed % levels.len() as u64) as usize];
+
+    state.level = level;
+    state.seed = seed;
+    state.mode = GameMode::Daily;
+    state.game_state = GameState::Init;
+
+    // the attempt is used up as soon as it starts
+    state.daily.results.push(DailyResult { day, level: level.to_string(), score: 0 });
+    storage::save(RESULTS_KEY, &state.daily.results);
+}
+
+pub fn finish(state: &mut State) {
+    let Some(result) = state.daily.results.last_mut() else { return };
+    result.score = state.score();
+    storage::save(RESULTS_KEY, &state.daily.results);
+}
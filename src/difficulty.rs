@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use super::{GameMode, State};
+use crate::config::GameConfig;
+
+// adaptive tiers go from -MAX_TIER to MAX_TIER
+const MAX_TIER: i32 = 2;
+// reputation lost since the last change that eases the game
+const LOSSES_TO_EASE: u32 = 2;
+// deliveries without a loss that ramp it up
+const STREAK_TO_RAMP: u32 = 5;
+// multiplier of a single tier
+const TIER_FACTOR: f32 = 1.15;
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    // starts as normal and follows the player's performance
+    Adaptive
+}
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Adaptive => "Adaptive"
+        }
+    }
+    pub fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Adaptive,
+            Difficulty::Adaptive => Difficulty::Easy
+        }
+    }
+    fn apply(&self, config: &mut GameConfig) {
+        match self {
+            Difficulty::Easy => {
+                config.reputation += 2;
+                config.passenger_max_wait *= 1.3;
+                config.stamina_recovery *= 1.25;
+                config.bird_speed *= 0.8;
+            },
+            Difficulty::Hard => {
+                config.reputation = config.reputation.saturating_sub(2).max(1);
+                config.passenger_max_wait *= 0.8;
+                config.stamina_recovery *= 0.85;
+                config.bird_speed *= 1.2;
+            },
+            Difficulty::Normal | Difficulty::Adaptive => ()
+        }
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Adaptive {
+    pub tier: i32,
+    losses: u32,
+    streak: u32,
+    // values seen on the previous step
+    reputation: u32,
+    score: u32
+}
+
+pub fn applies(mode: GameMode) -> bool {
+    // competitive and shared-seed modes always run at normal difficulty
+    matches!(mode, GameMode::FreePlay | GameMode::Campaign(_) | GameMode::CoOp)
+}
+
+pub fn selected(state: &State) -> Difficulty {
+    // what a new run of the current mode starts with
+    match applies(state.mode) {
+        true => state.settings.difficulty,
+        false => Difficulty::Normal
+    }
+}
+
+pub fn current(state: &State) -> Difficulty {
+    state.difficulty
+}
+
+pub fn start(state: &mut State, difficulty: Difficulty) {
+    // called once the level config is loaded
+    state.difficulty = difficulty;
+    difficulty.apply(&mut state.config);
+    state.adaptive = Adaptive {
+        reputation: state.config.reputation,
+        ..Default::default()
+    };
+}
+
+pub fn apply_tier(config: &mut GameConfig, tier: i32) {
+    // higher tiers spawn more often and give less time
+    let factor = TIER_FACTOR.powi(tier);
+    config.spawn_interval /= factor;
+    config.passenger_max_wait /= factor;
+    config.stamina_recovery /= factor;
+    config.bird_speed *= factor;
+}
+
+pub fn update_adaptive(state: &mut State) {
+    if current(state) != Difficulty::Adaptive { return }
+    let score = state.score();
+    let adaptive = &mut state.adaptive;
+    if state.reputation < adaptive.reputation {
+        adaptive.losses += adaptive.reputation - state.reputation;
+        adaptive.streak = 0;
+    }
+    if score > adaptive.score {
+        adaptive.streak += score - adaptive.score;
+    }
+    adaptive.reputation = state.reputation;
+    adaptive.score = score;
+
+    let change = if adaptive.losses >= LOSSES_TO_EASE && adaptive.tier > -MAX_TIER {
+        -1
+    } else if adaptive.streak >= STREAK_TO_RAMP && adaptive.tier < MAX_TIER {
+        1
+    } else {
+        return
+    };
+    adaptive.tier += change;
+    adaptive.losses = 0;
+    adaptive.streak = 0;
+    apply_tier(&mut state.config, change);
+}
+
+pub fn hud_text(state: &State) -> Option<String> {
+    let difficulty = current(state);
+    match difficulty {
+        Difficulty::Normal => None,
+        Difficulty::Adaptive => {
+            let tier = match state.adaptive.tier {
+                t if t <= -2 => "Relaxed",
+                -1 => "Easier",
+                0 => "Steady",
+                1 => "Harder",
+                _ => "Intense"
+            };
+            Some(format!("Tier: {}", tier))
+        },
+        _ => Some(difficulty.name().to_string())
+    }
+}
@@ -0,0 +1,126 @@
+use rogalik_engine::{Color, GraphicsContext, Params2d};
+use rogalik_math::vectors::Vector2f;
+
+use super::{Context_, GameState, State};
+use crate::globals::{BOARD_HEIGHT, BOARD_WIDTH, TRANSITION_Z};
+
+// camera offset in tiles at full trauma
+const MAX_SHAKE: f32 = 0.25;
+// trauma lost per second
+const SHAKE_DECAY: f32 = 2.5;
+const HIT_TRAUMA: f32 = 0.6;
+// seconds the simulation freezes on a hit
+const HIT_STOP: f32 = 0.08;
+const TRANSITION_TIME: f32 = 0.4;
+// a full block of the ascii atlas, tinted for the overlays
+const OVERLAY_SPRITE: usize = 219;
+
+#[derive(Clone, Copy)]
+enum TransitionKind {
+    // from black
+    Fade,
+    // to black and back, the screen behind keeps showing the last frame
+    FadeThrough,
+    // a curtain sliding off to the right
+    Wipe
+}
+
+#[derive(Default)]
+pub struct Effects {
+    // 0 to 1, the shake grows with its square
+    trauma: f32,
+    hit_stop: f32,
+    transition: Option<(TransitionKind, f32)>
+}
+impl Effects {
+    pub fn hit(&mut self) {
+        self.trauma = (self.trauma + HIT_TRAUMA).min(1.);
+        self.hit_stop = HIT_STOP;
+    }
+}
+
+pub fn game_delta(state: &mut State, delta: f32) -> f32 {
+    // the simulation delta, zero while the hit-stop lasts
+    let effects = &mut state.effects;
+    if state.settings.reduce_motion || effects.hit_stop <= 0. {
+        effects.hit_stop = 0.;
+        return delta
+    }
+    effects.hit_stop -= delta;
+    0.
+}
+
+pub fn change_screen(state: &mut State, from: GameState) {
+    // only the moves between the menus and the game get a transition
+    // (init lasts a single frame, so the wipe started before it carries on)
+    let kind = match (from, state.game_state) {
+        (GameState::MainMenu, GameState::Init) | (GameState::LevelSelect, GameState::Init)
+            | (GameState::MainMenu, GameState::Play) => TransitionKind::Wipe,
+        // the game over screen is drawn over the final board,
+        // so it starts from the board rather than from black
+        (GameState::Play, GameState::GameOver) => TransitionKind::FadeThrough,
+        (GameState::GameOver, GameState::MainMenu) => TransitionKind::Fade,
+        _ => return
+    };
+    // sliding shapes count as motion
+    let kind = match kind {
+        TransitionKind::Wipe if state.settings.reduce_motion => TransitionKind::Fade,
+        _ => kind
+    };
+    state.effects.transition = Some((kind, 0.));
+    state.effects.trauma = 0.;
+}
+
+pub fn update_effects(state: &mut State, context: &mut Context_) {
+    let delta = context.time.get_delta();
+    let effects = &mut state.effects;
+    effects.trauma = (effects.trauma - SHAKE_DECAY * delta).max(0.);
+    if let Some((_, elapsed)) = effects.transition.as_mut() {
+        *elapsed += delta;
+        if *elapsed >= TRANSITION_TIME {
+            effects.transition = None;
+        }
+    }
+
+    let mut target = board_centre();
+    let shake = state.settings.screen_shake && !state.settings.reduce_motion;
+    if shake && state.game_state == GameState::Play {
+        let amount = MAX_SHAKE * effects.trauma * effects.trauma;
+        target += amount * Vector2f::new(
+            2. * rand::random::<f32>() - 1.,
+            2. * rand::random::<f32>() - 1.
+        );
+    }
+    if let Some(camera) = context.graphics.get_camera_mut(state.camera_main) {
+        camera.set_target(target);
+    }
+}
+
+pub fn render_transition(state: &State, context: &mut Context_) {
+    let Some((kind, elapsed)) = state.effects.transition else { return };
+    let t = (elapsed / TRANSITION_TIME).min(1.);
+    // a margin, so a shaking camera never shows the edges
+    let size = crate::ui::view_size(state, context) + Vector2f::new(2., 2.);
+    let origin = board_centre() - 0.5 * size;
+    let (origin, size, alpha) = match kind {
+        TransitionKind::Fade => (origin, size, 1. - t),
+        TransitionKind::FadeThrough => (origin, size, 1. - (2. * t - 1.).abs()),
+        TransitionKind::Wipe => (
+            origin + Vector2f::new(t * size.x, 0.),
+            Vector2f::new((1. - t) * size.x, size.y),
+            1.
+        )
+    };
+    context.graphics.draw_atlas_sprite(
+        "ascii",
+        OVERLAY_SPRITE,
+        origin,
+        TRANSITION_Z,
+        size,
+        Params2d { color: Color(3, 2, 2, (255. * alpha) as u8), ..Default::default() }
+    );
+}
+
+fn board_centre() -> Vector2f {
+    Vector2f::new(BOARD_WIDTH as f32 / 2., BOARD_HEIGHT as f32 / 2.)
+}
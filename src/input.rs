@@ -0,0 +1,373 @@
+use rogalik_engine::input::VirtualKeyCode;
+
+#[cfg(not(target_arch="wasm32"))]
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use super::Context_;
+use crate::bindings::{Action, Bindings};
+
+// stick deflection counted as a direction press
+const STICK_THRESHOLD: f32 = 0.5;
+// stick values below are treated as zero
+const DEAD_ZONE: f32 = 0.15;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+    Pause
+}
+impl Command {
+    fn keys(&self) -> &'static [VirtualKeyCode] {
+        // fixed keys, confirm and pause also have rebindable ones
+        match self {
+            Command::Up => &[VirtualKeyCode::Up, VirtualKeyCode::W],
+            Command::Down => &[VirtualKeyCode::Down, VirtualKeyCode::S],
+            Command::Left => &[VirtualKeyCode::Left, VirtualKeyCode::A],
+            Command::Right => &[VirtualKeyCode::Right, VirtualKeyCode::D],
+            Command::Confirm => &[VirtualKeyCode::Return],
+            Command::Back => &[VirtualKeyCode::Escape],
+            Command::Pause => &[VirtualKeyCode::P]
+        }
+    }
+    fn from_button(button: PadButton) -> Self {
+        match button {
+            PadButton::Up => Command::Up,
+            PadButton::Down => Command::Down,
+            PadButton::Left => Command::Left,
+            PadButton::Right => Command::Right,
+            PadButton::South => Command::Confirm,
+            PadButton::East => Command::Back,
+            PadButton::Start => Command::Pause
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    South,
+    East,
+    Start
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PadAxis {
+    // -1 to 1, up and right are positive
+    StickX,
+    StickY,
+    // 0 to 1
+    Trigger
+}
+
+#[derive(Clone, Copy)]
+pub enum PadEvent {
+    Pressed(PadButton),
+    Moved(PadAxis, f32)
+}
+
+pub trait Gamepads {
+    // events of all the connected gamepads since the last call
+    fn poll(&mut self) -> Vec<PadEvent>;
+    // gamepads are indexed in the connection order
+    fn is_down(&self, pad: usize, button: PadButton) -> bool;
+    fn value(&self, pad: usize, axis: PadAxis) -> f32;
+}
+
+#[derive(Default)]
+pub struct FakePads {
+    // scripted gamepad state, also a stand-in when no backend is available
+    pub events: Vec<PadEvent>,
+    pub down: Vec<(usize, PadButton)>,
+    pub values: Vec<(usize, PadAxis, f32)>
+}
+impl Gamepads for FakePads {
+    fn poll(&mut self) -> Vec<PadEvent> {
+        std::mem::take(&mut self.events)
+    }
+    fn is_down(&self, pad: usize, button: PadButton) -> bool {
+        self.down.contains(&(pad, button))
+    }
+    fn value(&self, pad: usize, axis: PadAxis) -> f32 {
+        self.values.iter()
+            .find(|(p, a, _)| *p == pad && *a == axis)
+            .map(|(_, _, v)| *v)
+            .unwrap_or(0.)
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub struct GilrsPads(Gilrs);
+
+#[cfg(not(target_arch="wasm32"))]
+impl GilrsPads {
+    fn button(button: PadButton) -> Button {
+        match button {
+            PadButton::Up => Button::DPadUp,
+            PadButton::Down => Button::DPadDown,
+            PadButton::Left => Button::DPadLeft,
+            PadButton::Right => Button::DPadRight,
+            PadButton::South => Button::South,
+            PadButton::East => Button::East,
+            PadButton::Start => Button::Start
+        }
+    }
+    fn pad_button(button: Button) -> Option<PadButton> {
+        match button {
+            Button::DPadUp => Some(PadButton::Up),
+            Button::DPadDown => Some(PadButton::Down),
+            Button::DPadLeft => Some(PadButton::Left),
+            Button::DPadRight => Some(PadButton::Right),
+            Button::South => Some(PadButton::South),
+            Button::East => Some(PadButton::East),
+            Button::Start => Some(PadButton::Start),
+            _ => None
+        }
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+impl Gamepads for GilrsPads {
+    fn poll(&mut self) -> Vec<PadEvent> {
+        // gilrs keeps track of the connected gamepads itself
+        let mut events = Vec::new();
+        while let Some(event) = self.0.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = GilrsPads::pad_button(button) {
+                        events.push(PadEvent::Pressed(button));
+                    }
+                },
+                EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                    events.push(PadEvent::Moved(PadAxis::StickX, value));
+                },
+                EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                    events.push(PadEvent::Moved(PadAxis::StickY, value));
+                },
+                _ => ()
+            }
+        }
+        events
+    }
+    fn is_down(&self, pad: usize, button: PadButton) -> bool {
+        let Some((_, gamepad)) = self.0.gamepads().nth(pad) else { return false };
+        gamepad.is_pressed(GilrsPads::button(button))
+    }
+    fn value(&self, pad: usize, axis: PadAxis) -> f32 {
+        let Some((_, gamepad)) = self.0.gamepads().nth(pad) else { return 0. };
+        match axis {
+            PadAxis::StickX => gamepad.value(Axis::LeftStickX),
+            PadAxis::StickY => gamepad.value(Axis::LeftStickY),
+            PadAxis::Trigger => gamepad.button_data(Button::RightTrigger2)
+                .map(|a| a.value())
+                .unwrap_or(0.)
+        }
+    }
+}
+
+pub struct InputContext {
+    pub bindings: Bindings,
+    pads: Box<dyn Gamepads>,
+    // commands issued by any gamepad during the current frame
+    pressed: Vec<Command>,
+    // last stick direction, to turn the stick into menu presses
+    stick: (i8, i8)
+}
+impl Default for InputContext {
+    fn default() -> Self {
+        InputContext::with_pads(Box::new(FakePads::default()))
+    }
+}
+impl InputContext {
+    pub fn new() -> Self {
+        let mut input = InputContext::with_pads(default_pads());
+        input.bindings = crate::bindings::load_bindings();
+        input
+    }
+    pub fn with_pads(pads: Box<dyn Gamepads>) -> Self {
+        InputContext {
+            bindings: Bindings::default(),
+            pads,
+            pressed: Vec::new(),
+            stick: (0, 0)
+        }
+    }
+    pub fn update(&mut self) {
+        // should be called once at the start of every frame
+        self.pressed.clear();
+        for event in self.pads.poll() {
+            match event {
+                PadEvent::Pressed(button) => self.pressed.push(Command::from_button(button)),
+                PadEvent::Moved(PadAxis::StickX, value) => {
+                    let dir = stick_direction(value);
+                    if dir != self.stick.0 {
+                        match dir {
+                            1 => self.pressed.push(Command::Right),
+                            -1 => self.pressed.push(Command::Left),
+                            _ => ()
+                        }
+                    }
+                    self.stick.0 = dir;
+                },
+                PadEvent::Moved(PadAxis::StickY, value) => {
+                    let dir = stick_direction(value);
+                    if dir != self.stick.1 {
+                        match dir {
+                            1 => self.pressed.push(Command::Up),
+                            -1 => self.pressed.push(Command::Down),
+                            _ => ()
+                        }
+                    }
+                    self.stick.1 = dir;
+                },
+                _ => ()
+            }
+        }
+    }
+    pub fn is_pressed(&self, command: Command, context: &Context_) -> bool {
+        // keyboard or any gamepad
+        let bound = match command {
+            Command::Confirm => self.bindings.is_pressed(Action::Confirm, context),
+            Command::Pause => self.bindings.is_pressed(Action::Pause, context),
+            _ => false
+        };
+        // the fixed pause key gives way when an action is bound to it
+        bound
+            || self.pressed.contains(&command)
+            || command.keys().iter()
+                .filter(|k| command != Command::Pause || !self.bindings.is_bound(**k))
+                .any(|k| context.input.is_key_pressed(*k))
+    }
+    pub fn pad_lift(&self, pad: usize) -> f32 {
+        // the trigger or the stick, digital buttons give full lift
+        if self.pads.is_down(pad, PadButton::Up) || self.pads.is_down(pad, PadButton::South) {
+            return 1.
+        }
+        self.pads.value(pad, PadAxis::Trigger)
+            .max(dead_zone(self.pads.value(pad, PadAxis::StickY)))
+            .clamp(0., 1.)
+    }
+    pub fn pad_horizontal(&self, pad: usize) -> f32 {
+        let dpad = self.pads.is_down(pad, PadButton::Right) as i32
+            - self.pads.is_down(pad, PadButton::Left) as i32;
+        if dpad != 0 { return dpad as f32 }
+        dead_zone(self.pads.value(pad, PadAxis::StickX)).clamp(-1., 1.)
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+fn default_pads() -> Box<dyn Gamepads> {
+    match Gilrs::new() {
+        Ok(gilrs) => Box::new(GilrsPads(gilrs)),
+        Err(_) => Box::new(FakePads::default())
+    }
+}
+
+#[cfg(target_arch="wasm32")]
+fn default_pads() -> Box<dyn Gamepads> {
+    // gamepads are not supported in the browser build
+    Box::new(FakePads::default())
+}
+
+fn stick_direction(value: f32) -> i8 {
+    if value > STICK_THRESHOLD { return 1 }
+    if value < -STICK_THRESHOLD { return -1 }
+    0
+}
+
+fn dead_zone(value: f32) -> f32 {
+    // rescaled, so the output still starts at zero
+    if value.abs() < DEAD_ZONE { return 0. }
+    value.signum() * (value.abs() - DEAD_ZONE) / (1. - DEAD_ZONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_pads(pads: FakePads) -> InputContext {
+        InputContext::with_pads(Box::new(pads))
+    }
+
+    fn feed(input: &mut InputContext, events: Vec<PadEvent>) {
+        // a frame with the given gamepad events
+        input.pads = Box::new(FakePads { events, ..Default::default() });
+        input.update();
+    }
+
+    #[test]
+    fn dead_zone_is_clamped_and_rescaled() {
+        let input = with_pads(FakePads {
+            values: vec![
+                (0, PadAxis::StickX, 0.1),
+                (0, PadAxis::StickY, -0.1),
+                (1, PadAxis::StickX, -1.),
+                (1, PadAxis::StickY, DEAD_ZONE + 0.5 * (1. - DEAD_ZONE)),
+                (2, PadAxis::StickY, -1.),
+                (2, PadAxis::Trigger, 1.5)
+            ],
+            ..Default::default()
+        });
+        assert_eq!(input.pad_horizontal(0), 0.);
+        assert_eq!(input.pad_lift(0), 0.);
+        assert_eq!(input.pad_horizontal(1), -1.);
+        assert!((input.pad_lift(1) - 0.5).abs() < 1e-5);
+        // a stick pushed down gives no lift, the trigger is capped
+        assert_eq!(input.pad_lift(2), 1.);
+    }
+
+    #[test]
+    fn dpad_overrides_the_stick() {
+        let input = with_pads(FakePads {
+            down: vec![(0, PadButton::Left), (0, PadButton::Up)],
+            values: vec![(0, PadAxis::StickX, 0.8), (0, PadAxis::StickY, 0.3)],
+            ..Default::default()
+        });
+        assert_eq!(input.pad_horizontal(0), -1.);
+        assert_eq!(input.pad_lift(0), 1.);
+    }
+
+    #[test]
+    fn stick_presses_once_per_crossing() {
+        let mut input = InputContext::default();
+        feed(&mut input, vec![PadEvent::Moved(PadAxis::StickX, 0.3)]);
+        assert!(input.pressed.is_empty());
+        feed(&mut input, vec![
+            PadEvent::Moved(PadAxis::StickX, 0.6),
+            PadEvent::Moved(PadAxis::StickX, 0.9)
+        ]);
+        assert!(input.pressed == vec![Command::Right]);
+        // held over the threshold
+        feed(&mut input, vec![PadEvent::Moved(PadAxis::StickX, 1.)]);
+        assert!(input.pressed.is_empty());
+        feed(&mut input, vec![PadEvent::Moved(PadAxis::StickX, 0.)]);
+        assert!(input.pressed.is_empty());
+        feed(&mut input, vec![PadEvent::Moved(PadAxis::StickX, 0.7)]);
+        assert!(input.pressed == vec![Command::Right]);
+        feed(&mut input, vec![PadEvent::Moved(PadAxis::StickY, -0.7)]);
+        assert!(input.pressed == vec![Command::Down]);
+        // buttons are pressed every time
+        feed(&mut input, vec![PadEvent::Pressed(PadButton::Start)]);
+        assert!(input.pressed == vec![Command::Pause]);
+    }
+
+    #[test]
+    fn second_pad_drives_player_two() {
+        let input = with_pads(FakePads {
+            down: vec![(1, PadButton::Right)],
+            values: vec![(1, PadAxis::Trigger, 0.4)],
+            ..Default::default()
+        });
+        assert_eq!(input.pad_horizontal(0), 0.);
+        assert_eq!(input.pad_lift(0), 0.);
+        assert_eq!(input.pad_horizontal(1), 1.);
+        assert!((input.pad_lift(1) - 0.4).abs() < 1e-6);
+    }
+}
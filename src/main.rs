@@ -1,8 +1,8 @@
 use rogalik_engine::{Context, GraphicsContext, EngineBuilder, Game, ResourceId, Color};
-use rogalik_engine::input::VirtualKeyCode;
 use rogalik_math::vectors::Vector2f;
 use rogalik_wgpu::WgpuContext;
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 #[cfg(target_arch="wasm32")]
 use wasm_bindgen::prelude::*;
@@ -10,79 +10,227 @@ use wasm_bindgen::prelude::*;
 type Context_ = Context<WgpuContext>;
 
 mod audio;
+mod autotile;
+mod background;
+mod batch;
+mod bindings;
 mod board;
+mod campaign;
+mod config;
 mod creatures;
+mod daily;
+mod difficulty;
+mod effects;
 mod globals;
+mod input;
+mod music;
+#[cfg(not(target_arch="wasm32"))]
+mod netplay;
+mod particles;
 mod passenger;
 mod player;
 mod render;
+mod save;
+mod scores;
+mod settings;
 mod sprite;
+mod storage;
+mod synth;
+mod time_attack;
 mod ui;
 mod utils;
+mod versus;
 
-#[derive(Default)]
+#[derive(Clone, Copy, Default, PartialEq)]
 enum GameState {
     #[default]
     MainMenu,
+    LevelSelect,
+    Settings,
+    Controls,
+    Connecting,
     Init,
     Play,
+    Paused,
+    LevelComplete,
     GameOver
 }
 
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+enum GameMode {
+    #[default]
+    FreePlay,
+    // index into the campaign level list
+    Campaign(usize),
+    TimeAttack,
+    Daily,
+    CoOp,
+    Versus,
+    // co-op with a remote peer
+    Online
+}
+
 #[derive(Default)]
 pub struct State {
     audio: audio::AudioContext,
+    input: input::InputContext,
+    settings: settings::Settings,
+    // balance values of the current level
+    config: config::GameConfig,
+    config_file: config::ConfigFile,
+    debug: config::DebugPanel,
+    // fixed when the run starts, so changing the settings does not affect it
+    difficulty: difficulty::Difficulty,
+    adaptive: difficulty::Adaptive,
+    music: music::MusicData,
+    particles: particles::Particles,
+    effects: effects::Effects,
+    backgrounds: background::Backgrounds,
+    static_batch: batch::StaticBatch,
+    tileset: autotile::Tileset,
+    menu: ui::MenuFocus,
     camera_main: ResourceId,
     game_state: GameState,
+    mode: GameMode,
+    campaign: campaign::Campaign,
+    time_attack: time_attack::TimeAttack,
+    daily: daily::Daily,
+    high_scores: scores::HighScores,
+    has_saved_run: bool,
     level: &'static str,
-    level_data: HashMap<&'static str, &'static str>,
+    // kept in the display order
+    level_data: Vec<(&'static str, &'static str)>,
     board: board::Board,
-    animation_timer: ResourceId,
-    player: player::Player,
+    animation_timer: utils::Ticker,
+    players: Vec<player::Player>,
+    // shared by all the taxis
+    reputation: u32,
     passengers: Vec<passenger::Passenger>,
     creatures: Vec<creatures::Creature>,
+    manifest: passenger::Manifest,
+    seed: u64,
+    rng: utils::GameRng,
+    level_time: f32,
     since_spawn: f32,
-    spawn_timer: ResourceId,
-    spawn_interval: f32
+    spawn_timer: utils::Ticker,
+    spawn_interval: f32,
+    #[cfg(not(target_arch="wasm32"))]
+    net_config: Option<netplay::Config>,
+    #[cfg(not(target_arch="wasm32"))]
+    session: Option<netplay::Session>
+}
+impl State {
+    fn score(&self) -> u32 {
+        self.players.iter().map(|a| a.stats.score).sum()
+    }
+    fn player_count(&self) -> usize {
+        match self.mode {
+            GameMode::CoOp | GameMode::Versus | GameMode::Online => 2,
+            _ => 1
+        }
+    }
+    fn level_entry(&self, name: &str) -> Option<(&'static str, &'static str)> {
+        self.level_data.iter().find(|(a, _)| *a == name).copied()
+    }
 }
 impl Game<WgpuContext> for State {
     fn setup(&mut self, context: &mut Context_) {
         load_assets(self, context);
     }
     fn update(&mut self, context: &mut Context_) {
+        self.input.update();
+        let screen = self.game_state;
         match self.game_state {
             GameState::MainMenu => {
                 ui::render_main_menu(self, context);
             },
+            GameState::LevelSelect => {
+                ui::render_level_select(self, context);
+            },
+            GameState::Settings => {
+                ui::render_settings(self, context);
+            },
+            GameState::Controls => {
+                ui::render_controls(self, context);
+            },
+            GameState::Connecting => {
+                connecting_loop(self, context);
+                ui::render_connecting(self, context);
+            },
             GameState::Init => {
                 game_init(self, context);
             }
             GameState::Play => {
+                config::update_debug_panel(self, context);
                 game_loop(self, context);
                 render::render_sprites(self, context);
                 ui::render_game_ui(self, context);
+                ui::render_debug_panel(self, context);
+            },
+            GameState::Paused => {
+                config::update_debug_panel(self, context);
+                paused_loop(self, context);
+                render::render_sprites(self, context);
+                ui::render_game_ui(self, context);
+                ui::render_pause_menu(self, context);
+                ui::render_debug_panel(self, context);
+            },
+            GameState::LevelComplete => {
+                level_complete_loop(self, context);
+                render::render_sprites(self, context);
+                ui::render_game_ui(self, context);
+                ui::render_level_complete(self, context);
             },
             GameState::GameOver => {
                 game_over_loop(self, context);
                 render::render_sprites(self, context);
                 ui::render_game_ui(self, context);
-                ui::render_game_over(self, context);
+                if self.mode == GameMode::Versus {
+                    ui::render_versus_result(self, context);
+                } else {
+                    ui::render_game_over(self, context);
+                }
             }
         }
+        if self.game_state != screen {
+            // every screen starts with the focus on its first widget
+            self.menu.focus = 0;
+            effects::change_screen(self, screen);
+        }
+        effects::update_effects(self, context);
+        effects::render_transition(self, context);
+        self.audio.tick(context.time.get_delta());
+        music::update_music(self, context.time.get_delta());
     }
 }
 
 fn main() {
+    #[cfg(not(target_arch="wasm32"))]
+    if std::env::args().nth(1).as_deref() == Some("--export-sounds") {
+        // tool mode, writes the synthesized sounds without starting the game
+        let dir = std::env::args().nth(2).unwrap_or("sounds".to_string());
+        if let Err(e) = synth::export_wavs(&dir) {
+            eprintln!("Can't export the sounds: {}", e);
+        }
+        return
+    }
     std::env::set_var("WINIT_UNIX_BACKEND", "x11");
     run();
 }
 
 #[cfg_attr(target_arch="wasm32", wasm_bindgen(start))]
 fn run() {
-    let state = State::default();
+    let mut state = State::default();
+    #[cfg(not(target_arch="wasm32"))]
+    {
+        state.net_config = netplay::config_from_args();
+    }
+    state.settings = settings::load_settings();
+    let (width, height) = state.settings.window_size;
     let engine = EngineBuilder::new()
         .with_title("Grrr!".to_string())
-        .with_logical_size(1024., 640.)
+        .with_logical_size(width as f32, height as f32)
+        .with_fullscreen(state.settings.fullscreen)
         .build(state);
     
     //state, 1024, 640, "Grota");
@@ -90,32 +238,102 @@ fn run() {
 }
 
 fn game_loop(state: &mut State, context: &mut Context_) {
-    // check loose condition
-    if state.player.stats.reputation == 0 {
-        state.game_state = GameState::GameOver;
+    // online games share a single clock, so they can not be paused
+    if state.mode != GameMode::Online && (is_pause_pressed(state, context) || utils::focus_lost(context)) {
+        state.game_state = GameState::Paused;
         return
     }
-    update_difficulty(state);
+    if state.mode == GameMode::Online {
+        #[cfg(not(target_arch="wasm32"))]
+        online_loop(state, context);
+        return
+    }
+    let player_count = state.players.len();
+    let inputs = (0..player_count)
+        .map(|i| {
+            let controls = player::get_controls(&state.input.bindings, i, player_count);
+            player::read_input(i, &controls, &state.input, context)
+        })
+        .collect::<Vec<_>>();
+    let delta = effects::game_delta(state, context.time.get_delta());
+    simulate(state, &inputs, delta);
+}
 
-    if context.input.is_key_down(VirtualKeyCode::W) || context.input.is_key_down(VirtualKeyCode::Up) {
-        player::handle_lift(&mut state.player, context.time.get_delta(), true);
+#[cfg(not(target_arch="wasm32"))]
+fn online_loop(state: &mut State, context: &mut Context_) {
+    let Some(mut session) = state.session.take() else { return };
+    let controls = player::get_controls(&state.input.bindings, 0, 1);
+    let input = player::read_input(0, &controls, &state.input, context);
+    for inputs in session.step(input, context.time.get_delta()) {
+        simulate(state, &inputs, globals::NET_TICK);
+        if !matches!(state.game_state, GameState::Play) { break }
+    }
+    if session.is_timed_out() {
+        state.game_state = GameState::GameOver;
+    }
+    state.session = Some(session);
+}
 
-    } else {
-        player::handle_lift(&mut state.player, context.time.get_delta(), false);
+fn simulate(state: &mut State, inputs: &[player::Input], delta: f32) {
+    // a single deterministic step, driven only by the inputs and the delta
+    // check loose condition
+    if state.reputation == 0 {
+        if state.mode == GameMode::Daily {
+            daily::finish(state);
+        }
+        // the score tables only rank normal difficulty runs
+        if state.mode == GameMode::FreePlay && difficulty::current(state) == difficulty::Difficulty::Normal {
+            scores::check_record(state);
+        }
+        state.game_state = GameState::GameOver;
+        return
+    }
+    if let GameMode::Campaign(idx) = state.mode {
+        match campaign::check_objectives(state, idx) {
+            campaign::ObjectiveStatus::Complete(stars) => {
+                state.campaign.complete_level(idx, stars);
+                state.game_state = GameState::LevelComplete;
+                return
+            },
+            campaign::ObjectiveStatus::Failed => {
+                state.game_state = GameState::GameOver;
+                return
+            },
+            campaign::ObjectiveStatus::InProgress => ()
+        }
     }
+    if state.mode == GameMode::Versus && versus::is_finished(state) {
+        state.game_state = GameState::GameOver;
+        return
+    }
+    if state.mode == GameMode::TimeAttack && time_attack::is_finished(state) {
+        time_attack::finish(state);
+        state.game_state = GameState::LevelComplete;
+        return
+    }
+    update_difficulty(state);
+    difficulty::update_adaptive(state);
+    state.level_time += delta;
 
-    if !state.player.grounded {
-        if context.input.is_key_down(VirtualKeyCode::D) || context.input.is_key_down(VirtualKeyCode::Right) {
-            state.player.a.x = globals::FLY_ACC;
+    for (player, input) in state.players.iter_mut().zip(inputs) {
+        player::handle_lift(player, delta, input.lift, &state.config);
+        if input.lift > 0. && player.stats.stamina > 0. {
+            // wing puffs below the taxi, thicker with more lift
+            let centre = player.sprite.centre();
+            let position = Vector2f::new(centre.x, player.sprite.position.y);
+            state.particles.stream("puff", position, 1., input.lift * delta);
         }
-        if context.input.is_key_down(VirtualKeyCode::A) || context.input.is_key_down(VirtualKeyCode::Left) {
-            state.player.a.x = -globals::FLY_ACC;
+
+        if !player.grounded && input.horizontal != 0. {
+            player.a.x = input.horizontal * state.config.fly_acc;
         }
     }
 
-    if context.time.get_timer(state.animation_timer).unwrap().is_finished() {
-        if state.player.a.y > 0. {
-            state.player.sprite.frame = (state.player.sprite.frame + 1) % globals::ACTOR_FRAMES;
+    if state.animation_timer.tick(delta) {
+        for player in state.players.iter_mut() {
+            if player.a.y > 0. {
+                player.sprite.frame = (player.sprite.frame + 1) % globals::ACTOR_FRAMES;
+            }
         }
         for passenger in state.passengers.iter_mut() {
             passenger.sprite.frame = (passenger.sprite.frame + 1) % globals::ACTOR_FRAMES;
@@ -128,13 +346,20 @@ fn game_loop(state: &mut State, context: &mut Context_) {
         }
         for creature in state.creatures.iter_mut() {
             creature.sprite.frame = (creature.sprite.frame + 1) % globals::ACTOR_FRAMES;
+            // birds are heard from beyond the screen edge
+            if matches!(creature.kind, creatures::CreatureKind::Bird) && creature.sprite.frame == 0 {
+                state.audio.emit_at(audio::SoundEvent::WingFlap, creature.sprite.centre().x);
+            }
         }
     }
-    if context.time.get_timer(state.spawn_timer).unwrap().is_finished() {
+    if state.spawn_timer.tick(delta) {
         passenger::try_spawn(state);
     }
 
-    passenger::handle_waiting(state, context.time.get_delta());
+    if state.mode == GameMode::Versus {
+        player::try_bump(state);
+    }
+    passenger::handle_waiting(state, delta);
     passenger::try_knock_down(state);
     passenger::try_load(state);
     passenger::try_unload(state);
@@ -142,72 +367,111 @@ fn game_loop(state: &mut State, context: &mut Context_) {
 
     creatures::check_interactions(state);
 
-    player::update_player(state, context.time.get_delta());
+    player::update_player(state, delta);
     for passenger in state.passengers.iter_mut() {
-        passenger::move_passenger(passenger, &state.player, context.time.get_delta());
+        let y = passenger.sprite.position.y;
+        passenger::move_passenger(passenger, &state.players, &state.config, delta);
+        if passenger.state == passenger::PassengerState::Falling && y >= 0. && passenger.sprite.position.y < 0. {
+            // hits the bottom of the board
+            let position = Vector2f::new(passenger.sprite.centre().x, 0.);
+            state.particles.burst("splat", position, 1.);
+        }
     }
     for creature in state.creatures.iter_mut() {
-        creatures::update_creature(creature, context.time.get_delta());
+        creatures::update_creature(creature, &state.config, delta);
+        if matches!(creature.kind, creatures::CreatureKind::Mammoth) {
+            let position = creature.sprite.centre() + 0.5 * creature.dir;
+            state.particles.stream("breath", position, creature.dir.x, delta);
+        }
+    }
+    state.particles.update(delta);
+    if state.mode == GameMode::TimeAttack {
+        time_attack::update(state, delta);
     }
 }
 
 fn update_difficulty(state: &mut State) {
-    let decr = state.player.stats.score / globals::SPAWN_DROP_EVERY;
-    state.spawn_interval = 1.0_f32.max(globals::BASE_SPAWN_INTERVAL - decr as f32);
+    let decr = state.score() / state.config.spawn_drop_every;
+    state.spawn_interval = 1.0_f32.max(state.config.spawn_interval - decr as f32);
 }
 
 fn game_init(state: &mut State, context: &mut Context_) {
+    if state.mode != GameMode::Daily && state.mode != GameMode::Online {
+        // the daily challenge seed is derived from the date instead
+        // and online games share the host's seed
+        state.seed = rand::random();
+    }
     reinit(state, context);
     load_level(state, context, state.level);
+    if state.mode == GameMode::TimeAttack {
+        time_attack::start(state);
+    }
     state.game_state = GameState::Play;
 }
 
 fn game_over_loop(state: &mut State, context: &mut Context_) {
-    if context.input.is_key_down(rogalik_engine::input::VirtualKeyCode::Space) {
+    if state.high_scores.is_entering() {
+        scores::handle_initials(state, context);
+        return
+    }
+    if state.input.is_pressed(input::Command::Confirm, context) {
+        end_session(state);
         state.game_state = GameState::MainMenu;
     };
 }
 
+fn paused_loop(state: &mut State, context: &mut Context_) {
+    // the simulation timers are not advanced, so everything stays frozen
+    if is_pause_pressed(state, context) || state.input.is_pressed(input::Command::Back, context) {
+        state.game_state = GameState::Play;
+    }
+}
+
+fn is_pause_pressed(state: &State, context: &Context_) -> bool {
+    state.input.is_pressed(input::Command::Pause, context)
+}
+
+fn connecting_loop(state: &mut State, context: &mut Context_) {
+    if state.input.is_pressed(input::Command::Back, context) {
+        end_session(state);
+        state.game_state = GameState::MainMenu;
+        return
+    }
+    #[cfg(not(target_arch="wasm32"))]
+    netplay::update_connecting(state, context.time.get_delta());
+}
+
+fn end_session(state: &mut State) {
+    #[cfg(not(target_arch="wasm32"))]
+    {
+        state.session = None;
+    }
+}
+
+fn level_complete_loop(state: &mut State, context: &mut Context_) {
+    if state.input.is_pressed(input::Command::Confirm, context) {
+        state.game_state = GameState::LevelSelect;
+    };
+}
+
 fn load_assets(state: &mut State, context: &mut Context_) {
-    state.level_data.insert("Tricity", include_str!("../assets/tricity.lvl"));
-    state.level_data.insert("Birdy", include_str!("../assets/birdy.lvl"));
-    state.level_data.insert("Mammoth Hotel", include_str!("../assets/mammoths.lvl"));
+    state.level_data = vec![
+        ("Tutorial", include_str!("../assets/tutorial.lvl")),
+        ("Tricity", include_str!("../assets/tricity.lvl")),
+        ("Birdy", include_str!("../assets/birdy.lvl")),
+        ("Mammoth Hotel", include_str!("../assets/mammoths.lvl"))
+    ];
+    state.input = input::InputContext::new();
+    state.campaign = campaign::load_campaign(include_str!("../assets/campaign.ron"));
+    state.time_attack = time_attack::load_records(state.level_data.iter().map(|a| &a.0));
+    state.daily = daily::load_results();
+    state.high_scores = scores::load_scores(state.level_data.iter().map(|a| &a.0));
+    state.has_saved_run = save::has_saved_run();
+    state.config_file = config::load_config();
 
-    context.graphics.load_sprite_atlas(
-        "ascii",
-        include_bytes!("../assets/ascii.png"),
-        16,
-        16,
-        None
-    );
-    context.graphics.load_sprite_atlas(
-        "tiles",
-        include_bytes!("../assets/tiles.png"),
-        4,
-        4,
-        None
-    );
-    context.graphics.load_sprite_atlas(
-        "actors",
-        include_bytes!("../assets/actors.png"),
-        4,
-        4,
-        None
-    );
-    context.graphics.load_sprite_atlas(
-        "creatures",
-        include_bytes!("../assets/creatures.png"),
-        4,
-        4,
-        None
-    );
-    context.graphics.load_sprite_atlas(
-        "ui",
-        include_bytes!("../assets/ui.png"),
-        4,
-        4,
-        None
-    );
+    for (name, data, grid) in sprite::ATLASES {
+        context.graphics.load_sprite_atlas(name, data, grid, grid, None);
+    }
 
     context.graphics.load_font(
         "default",
@@ -218,38 +482,67 @@ fn load_assets(state: &mut State, context: &mut Context_) {
     );
 
     state.audio = audio::get_audio_context();
+    state.audio.set_volume(
+        state.settings.master_volume,
+        state.settings.sfx_volume,
+        state.settings.music_volume
+    );
+    state.music = music::load_music(&mut state.audio);
+    state.particles = particles::load_particles();
+    state.backgrounds = background::load_backgrounds();
+    state.tileset = autotile::load_tileset();
+
+    create_camera(state, context);
 
+    context.graphics.set_clear_color(Color(3, 2, 2, 255));
+}
+
+fn create_camera(state: &mut State, context: &mut Context_) {
+    // also called when the pixel scale setting changes
     state.camera_main = context.graphics.create_camera(
-        globals::PIXEL_SCALE, Vector2f::new(globals::BOARD_WIDTH as f32 / 2., globals::BOARD_HEIGHT as f32 / 2.)
+        state.settings.pixel_scale,
+        Vector2f::new(globals::BOARD_WIDTH as f32 / 2., globals::BOARD_HEIGHT as f32 / 2.)
     );
     context.graphics.set_camera(state.camera_main);
-
-    state.animation_timer = context.time.add_timer(globals::ANIMATION_TICK);
-    state.spawn_timer = context.time.add_timer(globals::SPAWN_TICK);
-    context.graphics.set_clear_color(Color(3, 2, 2, 255));
 }
 
 fn load_level(state: &mut State, context: &mut Context_, name: &str) {
-    let data = state.level_data.get(name).expect("Level data not found!");
-    (state.board, state.creatures) = board::generate_board(data);
+    let (_, data) = state.level_entry(name).expect("Level data not found!");
+    let (board_data, manifest_data) = board::split_level_data(data);
+    (state.board, state.creatures) = board::generate_board(board_data, &state.tileset);
+    state.manifest = passenger::parse_manifest(manifest_data);
+    batch::build_static_batch(state, context);
 }
 
 fn reinit(state: &mut State, context: &mut Context_) {
     // reinitialize the game state for a fresh game or restart
-    state.player = player::Player::new(
-        Vector2f::new((globals::BOARD_WIDTH / 2) as f32, 2.),
-        crate::globals::PLAYER_Z,
-        "actors",
-        0,
-        Color(255, 255, 255, 255),
-        Vector2f::new(globals::TILE_SIZE, globals::TILE_SIZE)
-    );
-    state.player.stats.reputation = globals::BASE_REPUTATION;
-    state.player.stats.stamina_use = globals::BASE_STAMINA_USE;
-    state.player.stats.stamina_recovery = globals::BASE_STAMINA_RECOVERY;
-    state.player.stats.stamina = 1.0;
-    state.player.passenger = None;
+    state.config = config::level_config(state);
+    state.debug.edits.clear();
+    difficulty::start(state, difficulty::selected(state));
+    let player_count = state.player_count();
+    state.players = (0..player_count).map(|i| {
+        // taxis are spread around the board centre
+        let x = (globals::BOARD_WIDTH / 2) as f32 + 2. * i as f32 - (player_count - 1) as f32;
+        let mut player = player::Player::new(
+            Vector2f::new(x, 2.),
+            crate::globals::PLAYER_Z,
+            "actors",
+            0,
+            globals::PLAYER_COLORS[i % globals::PLAYER_COLORS.len()],
+            Vector2f::new(globals::TILE_SIZE, globals::TILE_SIZE)
+        );
+        player.stats.stamina_use = state.config.stamina_use;
+        player.stats.stamina = 1.0;
+        player
+    }).collect();
+    state.reputation = state.config.reputation;
     state.passengers = Vec::new();
-    state.spawn_interval = globals::BASE_SPAWN_INTERVAL;
+    state.particles.clear();
+    state.spawn_interval = state.config.spawn_interval;
     state.since_spawn = 0.;
+    state.level_time = 0.;
+    state.high_scores.new_record = None;
+    state.rng = utils::GameRng::new(state.seed);
+    state.animation_timer = utils::Ticker::new(globals::ANIMATION_TICK);
+    state.spawn_timer = utils::Ticker::new(globals::SPAWN_TICK);
 }
\ No newline at end of file
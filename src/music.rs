@@ -0,0 +1,92 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{GameState, State};
+use crate::audio::AudioContext;
+use crate::passenger::PassengerState;
+use crate::synth::{self, SynthParams};
+
+// share of the max wait after which a passenger is about to resign
+const URGENT_WAIT: f32 = 0.75;
+// intensity change of a single adaptive difficulty tier
+const TIER_INTENSITY: f32 = 0.15;
+
+#[derive(Deserialize)]
+struct StemData {
+    file: String,
+    from: f32,
+    // played while the file is missing
+    #[serde(default)]
+    synth: Option<SynthStem>
+}
+
+#[derive(Deserialize)]
+struct SynthStem {
+    sound: SynthParams,
+    notes: String,
+    step: f32
+}
+
+#[derive(Default, Deserialize)]
+pub struct MusicData {
+    menu: String,
+    // used by levels without an entry
+    default: String,
+    levels: HashMap<String, String>,
+    tracks: HashMap<String, Vec<StemData>>
+}
+
+pub fn load_music(audio: &mut AudioContext) -> MusicData {
+    let data: MusicData = ron::from_str(include_str!("../assets/music.ron"))
+        .expect("Invalid music data!");
+    for (name, stems) in data.tracks.iter() {
+        let stems = stems.iter()
+            .filter_map(|a| Some((read_stem(&a.file).or_else(|| synth_stem(a))?, a.from)))
+            .collect();
+        audio.add_track(name, stems);
+    }
+    data
+}
+
+#[cfg(not(target_arch="wasm32"))]
+fn read_stem(file: &str) -> Option<Vec<u8>> {
+    // the stems are large, so they are not embedded in the binary
+    std::fs::read(std::path::Path::new("assets/music").join(file)).ok()
+}
+
+#[cfg(target_arch="wasm32")]
+fn read_stem(_file: &str) -> Option<Vec<u8>> {
+    // the browser build only plays the synthesized stems
+    None
+}
+
+fn synth_stem(stem: &StemData) -> Option<Vec<u8>> {
+    let data = stem.synth.as_ref()?;
+    Some(synth::to_wav(&synth::sequence(&data.sound, &data.notes, data.step)))
+}
+
+pub fn update_music(state: &mut State, delta: f32) {
+    // crossfades between the menu and level tracks
+    let track = match state.game_state {
+        GameState::MainMenu | GameState::LevelSelect | GameState::Settings
+            | GameState::Controls | GameState::Connecting => &state.music.menu,
+        _ => state.music.levels.get(state.level).unwrap_or(&state.music.default)
+    };
+    let intensity = intensity(state);
+    state.audio.update_music(Some(track.as_str()), intensity, delta);
+}
+
+fn intensity(state: &State) -> f32 {
+    // only the running game builds up
+    if state.game_state != GameState::Play { return 0. }
+    // the spawn interval shortens with deliveries, down to a second
+    let range = (state.config.spawn_interval - 1.).max(1.);
+    let spawn = (state.config.spawn_interval - state.spawn_interval) / range;
+    let tier = state.adaptive.tier as f32 * TIER_INTENSITY;
+    let urgent = state.passengers.iter().any(|a| match a.state {
+        PassengerState::Waiting(time) => time >= URGENT_WAIT * a.kind.max_wait(&state.config),
+        _ => false
+    });
+    if urgent { return 1. }
+    (spawn + tier).clamp(0., 1.)
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use super::{GameState, State};
+use crate::globals::{NET_INPUT_DELAY, NET_TICK, NET_TIMEOUT};
+use crate::player::Input;
+
+// lockstep co-op over udp:
+// both peers run the same simulation and only exchange per tick inputs.
+// local inputs are applied NET_INPUT_DELAY ticks later, to give them time
+// to reach the other side. the simulation stalls when the peer's input is missing.
+// try it locally with `grrr --host 7777` and `grrr --join 127.0.0.1:7777`
+
+const MSG_HELLO: u8 = 0;
+const MSG_START: u8 = 1;
+const MSG_INPUTS: u8 = 2;
+// every input packet repeats the recent inputs, so single lost packets do not matter
+const REDUNDANCY: u64 = 8;
+const MAX_BACKLOG: f32 = 8.;
+const HELLO_INTERVAL: f32 = 0.5;
+
+#[derive(Clone)]
+pub enum Config {
+    Host(u16),
+    Join(SocketAddr)
+}
+
+pub fn config_from_args() -> Option<Config> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let value = args.get(2)?;
+    match args.get(1)?.as_str() {
+        "--host" => value.parse().ok().map(Config::Host),
+        "--join" => value.to_socket_addrs().ok()?.next().map(Config::Join),
+        _ => None
+    }
+}
+
+pub struct Session {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    is_host: bool,
+    // seed and level, sent by the host
+    start: Option<(u64, String)>,
+    tick: u64,
+    next_scheduled: u64,
+    local: HashMap<u64, u8>,
+    remote: HashMap<u64, u8>,
+    accumulator: f32,
+    since_sent: f32,
+    since_received: f32
+}
+impl Session {
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        let (socket, peer, is_host) = match config {
+            Config::Host(port) => (UdpSocket::bind(("0.0.0.0", *port))?, None, true),
+            Config::Join(addr) => (UdpSocket::bind("0.0.0.0:0")?, Some(*addr), false)
+        };
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            peer,
+            is_host,
+            start: None,
+            tick: 0,
+            next_scheduled: NET_INPUT_DELAY,
+            local: HashMap::new(),
+            remote: HashMap::new(),
+            accumulator: 0.,
+            since_sent: HELLO_INTERVAL,
+            since_received: 0.
+        })
+    }
+    pub fn is_timed_out(&self) -> bool {
+        self.since_received > NET_TIMEOUT
+    }
+    pub fn step(&mut self, input: Input, delta: f32) -> Vec<[Input; 2]> {
+        // returns the inputs of every tick ready to simulate, host's player first
+        self.receive();
+        self.since_received += delta;
+        self.accumulator = (self.accumulator + delta).min(MAX_BACKLOG * NET_TICK);
+
+        let mut ticks = Vec::new();
+        while self.accumulator >= NET_TICK {
+            while self.next_scheduled <= self.tick + NET_INPUT_DELAY {
+                self.local.insert(self.next_scheduled, encode_input(input));
+                self.next_scheduled += 1;
+            }
+            let (Some(local), Some(remote)) = (
+                input_at(&self.local, self.tick),
+                input_at(&self.remote, self.tick)
+            ) else { break };
+
+            let mut inputs = [decode_input(local), decode_input(remote)];
+            if !self.is_host { inputs.swap(0, 1) }
+            ticks.push(inputs);
+            self.tick += 1;
+            self.accumulator -= NET_TICK;
+        }
+        self.local.retain(|t, _| *t + REDUNDANCY >= self.tick);
+        self.remote.retain(|t, _| *t >= self.tick);
+        self.send_inputs();
+        ticks
+    }
+    fn host_poll(&mut self, seed: u64, level: &str) -> bool {
+        // waits for a hello, then tells the peer what to play
+        self.receive();
+        if self.peer.is_none() { return false }
+        self.start = Some((seed, level.to_string()));
+        self.send_start(seed, level);
+        true
+    }
+    fn join_poll(&mut self, delta: f32) -> Option<&(u64, String)> {
+        // says hello until the host answers with the start message
+        self.receive();
+        self.since_sent += delta;
+        if self.since_sent >= HELLO_INTERVAL {
+            self.since_sent = 0.;
+            self.send(&[MSG_HELLO]);
+        }
+        self.start.as_ref()
+    }
+    fn send(&self, data: &[u8]) {
+        let Some(peer) = self.peer else { return };
+        let _ = self.socket.send_to(data, peer);
+    }
+    fn send_start(&self, seed: u64, level: &str) {
+        let mut data = vec![MSG_START];
+        data.extend_from_slice(&seed.to_le_bytes());
+        data.extend_from_slice(level.as_bytes());
+        self.send(&data);
+    }
+    fn send_inputs(&self) {
+        let first = NET_INPUT_DELAY.max(self.next_scheduled.saturating_sub(REDUNDANCY));
+        let inputs = (first..self.next_scheduled)
+            .filter_map(|t| self.local.get(&t).copied())
+            .collect::<Vec<_>>();
+        if inputs.len() == 0 { return }
+        let mut data = vec![MSG_INPUTS];
+        data.extend_from_slice(&first.to_le_bytes());
+        data.push(inputs.len() as u8);
+        data.extend(inputs);
+        self.send(&data);
+    }
+    fn receive(&mut self) {
+        let mut buf = [0; 512];
+        while let Ok((len, src)) = self.socket.recv_from(&mut buf) {
+            // the host accepts the first peer that says hello
+            if self.is_host && self.peer.is_none() && len > 0 && buf[0] == MSG_HELLO {
+                self.peer = Some(src);
+            }
+            if self.peer != Some(src) { continue }
+            self.since_received = 0.;
+            self.handle_packet(&buf[..len]);
+        }
+    }
+    fn handle_packet(&mut self, data: &[u8]) {
+        match data.first() {
+            Some(&MSG_HELLO) => {
+                // the start message might have been lost, repeat it
+                if let Some((seed, level)) = &self.start {
+                    self.send_start(*seed, level);
+                }
+            },
+            Some(&MSG_START) if !self.is_host && data.len() >= 9 => {
+                let seed = u64::from_le_bytes(data[1..9].try_into().unwrap());
+                let level = String::from_utf8_lossy(&data[9..]).to_string();
+                self.start = Some((seed, level));
+            },
+            Some(&MSG_INPUTS) if data.len() >= 10 => {
+                let first = u64::from_le_bytes(data[1..9].try_into().unwrap());
+                let count = data[9] as usize;
+                for (i, input) in data[10..].iter().take(count).enumerate() {
+                    self.remote.insert(first + i as u64, *input);
+                }
+            },
+            _ => ()
+        }
+    }
+}
+
+pub fn update_connecting(state: &mut State, delta: f32) {
+    let Some(config) = state.net_config.clone() else {
+        state.game_state = GameState::MainMenu;
+        return
+    };
+    if state.session.is_none() {
+        let Ok(session) = Session::new(&config) else {
+            state.game_state = GameState::MainMenu;
+            return
+        };
+        if session.is_host {
+            state.seed = rand::random();
+        }
+        state.session = Some(session);
+    }
+    let Some(session) = state.session.as_mut() else { return };
+
+    if session.is_host {
+        if session.host_poll(state.seed, state.level) {
+            state.game_state = GameState::Init;
+        }
+        return
+    }
+
+    let Some((seed, level)) = session.join_poll(delta) else { return };
+    let Some((level, _)) = state.level_data.iter().find(|(a, _)| a == level) else {
+        state.game_state = GameState::MainMenu;
+        return
+    };
+    state.level = *level;
+    state.seed = *seed;
+    state.game_state = GameState::Init;
+}
+
+fn input_at(inputs: &HashMap<u64, u8>, tick: u64) -> Option<u8> {
+    // nobody can act during the initial delay
+    if tick < NET_INPUT_DELAY { return Some(0) }
+    inputs.get(&tick).copied()
+}
+
+fn encode_input(input: Input) -> u8 {
+    // lift in the low nibble, the horizontal sign and magnitude in the high one
+    // (so a zero byte is still the idle input)
+    let lift = (input.lift.clamp(0., 1.) * 15.).round() as u8;
+    let horizontal = (input.horizontal.abs().min(1.) * 7.).round() as u8;
+    let sign = (input.horizontal < 0.) as u8;
+    lift | horizontal << 4 | sign << 7
+}
+
+fn decode_input(value: u8) -> Input {
+    let sign = if value & 0x80 != 0 { -1. } else { 1. };
+    Input {
+        lift: (value & 0x0f) as f32 / 15.,
+        horizontal: sign * ((value >> 4) & 0x07) as f32 / 7.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const STEPS: usize = 500;
+
+    fn connect() -> (Session, Session) {
+        let mut host = Session::new(&Config::Host(0)).unwrap();
+        let port = host.socket.local_addr().unwrap().port();
+        let mut client = Session::new(&Config::Join(SocketAddr::from(([127, 0, 0, 1], port)))).unwrap();
+
+        let mut started = false;
+        for _ in 0..STEPS {
+            started |= host.host_poll(7, "Test");
+            if let Some(start) = client.join_poll(NET_TICK) {
+                assert_eq!(start, &(7, "Test".to_string()));
+                assert!(started);
+                return (host, client)
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("handshake timed out");
+    }
+
+    #[test]
+    fn inputs_cross_the_delay_window() {
+        let (mut host, mut client) = connect();
+        let host_input = Input { lift: 1., horizontal: -1. };
+        let client_input = Input { lift: 0., horizontal: 1. };
+        let count = 4 * NET_INPUT_DELAY as usize;
+
+        let mut host_ticks = Vec::new();
+        let mut client_ticks = Vec::new();
+        for _ in 0..STEPS {
+            host_ticks.extend(host.step(host_input, NET_TICK));
+            client_ticks.extend(client.step(client_input, NET_TICK));
+            if host_ticks.len() >= count && client_ticks.len() >= count { break }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(host_ticks.len() >= count && client_ticks.len() >= count);
+
+        let encode = |ticks: &[[Input; 2]]| ticks.iter()
+            .map(|a| [encode_input(a[0]), encode_input(a[1])])
+            .collect::<Vec<_>>();
+        let host_ticks = encode(&host_ticks[..count]);
+        let client_ticks = encode(&client_ticks[..count]);
+        // both sides simulate the same inputs, host's player first
+        assert_eq!(host_ticks, client_ticks);
+        for (tick, inputs) in host_ticks.iter().enumerate() {
+            let expected = if (tick as u64) < NET_INPUT_DELAY {
+                [0, 0]
+            } else {
+                [encode_input(host_input), encode_input(client_input)]
+            };
+            assert_eq!(*inputs, expected);
+        }
+    }
+}
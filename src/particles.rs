@@ -0,0 +1,163 @@
+use rogalik_engine::{Color, GraphicsContext, Params2d};
+use rogalik_math::vectors::Vector2f;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+use super::{Context_, State};
+use crate::globals::PARTICLE_Z;
+use crate::utils::pixel_perfect;
+
+// oldest particles are dropped above it
+const MAX_PARTICLES: usize = 512;
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct EmitterSpec {
+    atlas: String,
+    index: usize,
+    // picked at random for every particle
+    colors: Vec<(u8, u8, u8, u8)>,
+    // particles of a single burst
+    count: u32,
+    // particles per second of a stream
+    rate: f32,
+    // degrees, 0 points right and 90 up, mirrored for left facing emitters
+    angle: f32,
+    spread: f32,
+    // (min, max) ranges
+    speed: (f32, f32),
+    life: (f32, f32),
+    // spawn position jitter
+    radius: f32,
+    gravity: f32,
+    drag: f32,
+    // size at birth and at death
+    size: (f32, f32),
+    fade: bool
+}
+impl Default for EmitterSpec {
+    fn default() -> Self {
+        EmitterSpec {
+            atlas: "ascii".to_string(),
+            index: 254,
+            colors: vec![(255, 255, 255, 255)],
+            count: 8,
+            rate: 20.,
+            angle: 90.,
+            spread: 360.,
+            speed: (0.5, 1.5),
+            life: (0.3, 0.6),
+            radius: 0.,
+            gravity: 0.,
+            drag: 0.,
+            size: (0.25, 0.),
+            fade: true
+        }
+    }
+}
+
+struct Particle {
+    spec: usize,
+    position: Vector2f,
+    v: Vector2f,
+    color: Color,
+    age: f32,
+    life: f32
+}
+
+#[derive(Default)]
+pub struct Particles {
+    specs: Vec<(String, EmitterSpec)>,
+    // oldest first, so the oldest is dropped at the cap
+    items: VecDeque<Particle>,
+    // fractional stream particles carried between frames, per spec
+    carry: Vec<f32>
+}
+impl Particles {
+    pub fn burst(&mut self, name: &str, position: Vector2f, facing: f32) {
+        let Some(spec) = self.spec_idx(name) else { return };
+        for _ in 0..self.specs[spec].1.count {
+            self.spawn(spec, position, facing);
+        }
+    }
+    pub fn stream(&mut self, name: &str, position: Vector2f, facing: f32, seconds: f32) {
+        // `seconds` can be scaled down for weaker emission
+        let Some(spec) = self.spec_idx(name) else { return };
+        self.carry[spec] += self.specs[spec].1.rate * seconds;
+        while self.carry[spec] >= 1. {
+            self.carry[spec] -= 1.;
+            self.spawn(spec, position, facing);
+        }
+    }
+    pub fn update(&mut self, delta: f32) {
+        for particle in self.items.iter_mut() {
+            let spec = &self.specs[particle.spec].1;
+            particle.age += delta;
+            particle.v.y -= spec.gravity * delta;
+            particle.v = particle.v * (1. - spec.drag * delta).max(0.);
+            particle.position += particle.v * delta;
+        }
+        self.items.retain(|a| a.age < a.life);
+    }
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+    fn spec_idx(&self, name: &str) -> Option<usize> {
+        self.specs.iter().position(|(a, _)| a == name)
+    }
+    fn spawn(&mut self, spec_idx: usize, position: Vector2f, facing: f32) {
+        if self.items.len() >= MAX_PARTICLES {
+            self.items.pop_front();
+        }
+        let spec = &self.specs[spec_idx].1;
+        let angle = (spec.angle + spec.spread * (rand::random::<f32>() - 0.5)).to_radians();
+        let speed = between(spec.speed);
+        let v = Vector2f::new(facing * speed * angle.cos(), speed * angle.sin());
+        let jitter = Vector2f::new(
+            spec.radius * (2. * rand::random::<f32>() - 1.),
+            spec.radius * (2. * rand::random::<f32>() - 1.)
+        );
+        let (r, g, b, a) = spec.colors.get(rand::random::<usize>() % spec.colors.len().max(1))
+            .copied()
+            .unwrap_or((255, 255, 255, 255));
+        self.items.push_back(Particle {
+            spec: spec_idx,
+            position: position + jitter,
+            v,
+            color: Color(r, g, b, a),
+            age: 0.,
+            life: between(spec.life)
+        });
+    }
+}
+
+pub fn load_particles() -> Particles {
+    let specs: Vec<(String, EmitterSpec)> = ron::from_str(include_str!("../assets/particles.ron"))
+        .expect("Invalid particle data!");
+    Particles { carry: vec![0.; specs.len()], specs, items: VecDeque::new() }
+}
+
+pub fn render_particles(state: &State, context: &mut Context_) {
+    for particle in state.particles.items.iter() {
+        let spec = &state.particles.specs[particle.spec].1;
+        let t = particle.age / particle.life;
+        let size = spec.size.0 + t * (spec.size.1 - spec.size.0);
+        let mut color = particle.color;
+        if spec.fade {
+            color.3 = (color.3 as f32 * (1. - t)) as u8;
+        }
+        context.graphics.draw_atlas_sprite(
+            &spec.atlas,
+            spec.index,
+            // the position is the particle's centre
+            pixel_perfect(particle.position - 0.5 * Vector2f::new(size, size), state.settings.pixel_scale),
+            PARTICLE_Z,
+            Vector2f::new(size, size),
+            Params2d { color, ..Default::default() }
+        );
+    }
+}
+
+fn between(range: (f32, f32)) -> f32 {
+    range.0 + rand::random::<f32>() * (range.1 - range.0)
+}
@@ -4,39 +4,75 @@ use rogalik_math::{
     aabb::Aabb,
     vectors::Vector2f
 };
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 use super::State;
+use crate::audio::SoundEvent;
 use crate::board::Gate;
+use crate::config::GameConfig;
 use crate::globals::{
-    PASSENGER_LOAD_DIST, PASSENGER_WALK_SPEED, TOLERANCE, TILE_SIZE, SPAWN_TICK,
-    PASSENGER_WIDTH, PASSENGER_HEIGHT, PASSENGER_FALL_SPEED, PASSENGER_KNOCK_DOWN_SPEED,
-    PASSENGER_MAX_WAIT
+    TOLERANCE, TILE_SIZE, SPAWN_TICK, PASSENGER_WIDTH, PASSENGER_HEIGHT
 };
-use crate::player::Player;
+use crate::player::{Player, take_reputation};
 use crate::sprite::DynamicSprite;
-use crate::utils::almost_eq;
+use crate::utils::{almost_eq, from_roman};
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Deserialize, Serialize)]
 pub enum PassengerState {
     Waiting(f32),
-    Landed(Vector2f),
+    Landed(#[serde(with = "crate::save::Vector2fDef")] Vector2f),
     Loaded,
     Falling,
     Resigned
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum PassengerKind {
+    #[default]
+    Regular,
+    Hasty,
+    Patient
+}
+impl PassengerKind {
+    pub fn max_wait(&self, config: &GameConfig) -> f32 {
+        match self {
+            PassengerKind::Regular => config.passenger_max_wait,
+            PassengerKind::Hasty => 0.5 * config.passenger_max_wait,
+            PassengerKind::Patient => 1.5 * config.passenger_max_wait
+        }
+    }
+    fn color(&self) -> Color {
+        match self {
+            PassengerKind::Regular => Color(255, 255, 255, 255),
+            PassengerKind::Hasty => Color(255, 190, 170, 255),
+            PassengerKind::Patient => Color(190, 220, 255, 255)
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "regular" => Some(PassengerKind::Regular),
+            "hasty" => Some(PassengerKind::Hasty),
+            "patient" => Some(PassengerKind::Patient),
+            _ => None
+        }
+    }
+}
+
+#[derive(PartialEq, Deserialize, Serialize)]
 pub enum PassengerAnimationState {
     Idle,
     Walking,
     Falling
 }
 
+#[derive(Deserialize, Serialize)]
 pub struct Passenger {
     pub sprite: DynamicSprite,
     pub state: PassengerState,
     pub source_gate: u32,
     pub target_gate: u32,
+    pub kind: PassengerKind,
     pub animation_state: PassengerAnimationState
 }
 impl Passenger {
@@ -48,7 +84,8 @@ impl Passenger {
         color: Color,
         collider_size: Vector2f,
         source_gate: u32,
-        target_gate: u32
+        target_gate: u32,
+        kind: PassengerKind
     ) -> Self {
         let sprite = DynamicSprite::new(
             position,
@@ -64,17 +101,82 @@ impl Passenger {
             state: PassengerState::Waiting(0.),
             target_gate,
             source_gate,
+            kind,
             animation_state: PassengerAnimationState::Idle
         }
     }
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub time: f32,
+    pub source_gate: u32,
+    pub target_gate: u32,
+    pub kind: PassengerKind
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct Manifest {
+    // sorted by spawn time
+    pub entries: VecDeque<ManifestEntry>,
+    // whether random passengers should spawn alongside the scripted ones
+    pub random: bool,
+    // whether they should start once the scripted ones run out
+    #[serde(default)]
+    pub then_random: bool
+}
+
+pub fn parse_manifest(data: &str) -> Manifest {
+    // a level without a manifest section is fully random
+    // entry format: `at <seconds> <source gate> <target gate> [kind]`
+    // `random` adds random passengers, `then random` only after the last entry
+    // gates are given in roman numerals, as displayed in game
+    let mut manifest = Manifest::default();
+    let mut has_entries = false;
+    let mut entries = Vec::new();
+
+    for line in data.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.len() == 0 { continue }
+        has_entries = true;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            ["random"] => manifest.random = true,
+            ["then", "random"] => manifest.then_random = true,
+            ["at", time, source, target, ref kind @ ..] => {
+                let entry = ManifestEntry {
+                    time: time.parse().expect(&format!("Incorrect manifest time: {}", line)),
+                    source_gate: from_roman(source).expect(&format!("Incorrect manifest gate: {}", line)) - 1,
+                    target_gate: from_roman(target).expect(&format!("Incorrect manifest gate: {}", line)) - 1,
+                    kind: match kind {
+                        [] => PassengerKind::Regular,
+                        [name] => PassengerKind::from_name(name)
+                            .expect(&format!("Incorrect passenger kind: {}", line)),
+                        _ => panic!("Incorrect manifest entry: {}", line)
+                    }
+                };
+                entries.push(entry);
+            },
+            _ => panic!("Incorrect manifest entry: {}", line)
+        }
+    }
+    if !has_entries { manifest.random = true };
+
+    entries.sort_by(|a, b| a.time.total_cmp(&b.time));
+    manifest.entries = entries.into();
+    manifest
+}
+
 pub fn try_spawn(state: &mut State) {
     state.since_spawn += SPAWN_TICK;
     for gate in state.board.gates.iter_mut() {
         gate.since_pickup += SPAWN_TICK;
     }
 
+    try_spawn_scripted(state);
+
+    let script_over = state.manifest.then_random && state.manifest.entries.is_empty();
+    if !state.manifest.random && !script_over { return }
     if state.since_spawn < state.spawn_interval { return }
 
     let source_candidates = state.board.gates.iter()
@@ -82,32 +184,57 @@ pub fn try_spawn(state: &mut State) {
         .filter(|(_, a)| !a.has_passenger && a.since_pickup > state.spawn_interval)
         .map(|(i, _)| i);
 
-    let mut rng = thread_rng();
-    let Some(gate_idx) = source_candidates.choose(&mut rng) else { return };
+    let Some(gate_idx) = source_candidates.choose(&mut state.rng) else { return };
     let target_candidates = state.board.gates.iter()
         .enumerate()
         .filter(|(i, _)| *i != gate_idx)
         .map(|(i, _)| i);
 
-    let Some(target_gate) = target_candidates.choose(&mut rng) else { return };
+    let Some(target_gate) = target_candidates.choose(&mut state.rng) else { return };
     
     if state.board.gates[gate_idx].has_passenger { return };
 
-    let gate_position = state.board.gates[gate_idx].position;
+    spawn_passenger(state, gate_idx as u32, target_gate as u32, PassengerKind::Regular);
+}
+
+fn try_spawn_scripted(state: &mut State) {
+    // due entries wait while their source gate is occupied
+    let mut i = 0;
+    while i < state.manifest.entries.len() {
+        let entry = &state.manifest.entries[i];
+        if entry.time > state.level_time { break }
+        let gate_count = state.board.gates.len() as u32;
+        if entry.source_gate >= gate_count || entry.target_gate >= gate_count {
+            // the manifest does not match the board, skip the entry
+            state.manifest.entries.remove(i);
+            continue;
+        }
+        if state.board.gates[entry.source_gate as usize].has_passenger {
+            i += 1;
+            continue;
+        }
+        let entry = state.manifest.entries.remove(i).unwrap();
+        spawn_passenger(state, entry.source_gate, entry.target_gate, entry.kind);
+    }
+}
+
+fn spawn_passenger(state: &mut State, source_gate: u32, target_gate: u32, kind: PassengerKind) {
+    let gate_position = state.board.gates[source_gate as usize].position;
 
     let passenger = Passenger::new(
         gate_position + Vector2f::new(0.5 * TILE_SIZE, 0.),
         crate::globals::PASSENGER_Z,
         "actors",
         4,
-        Color(255, 255, 255, 255),
+        kind.color(),
         Vector2f::new(PASSENGER_WIDTH, PASSENGER_HEIGHT),
-        gate_idx as u32,
-        target_gate as u32
+        source_gate,
+        target_gate,
+        kind
     );
     state.passengers.push(passenger);
     state.since_spawn = 0.;
-    state.board.gates[gate_idx].has_passenger = true;
+    state.board.gates[source_gate as usize].has_passenger = true;
 }
 
 pub fn should_remove(passenger: &Passenger) -> bool {
@@ -127,25 +254,26 @@ pub fn handle_waiting(state: &mut State, delta: f32) {
     for passenger in state.passengers.iter_mut() {
         if let PassengerState::Waiting(ref mut time) = passenger.state {
             *time += delta;
-            if *time >= PASSENGER_MAX_WAIT && passenger.animation_state == PassengerAnimationState::Idle {
-                state.player.stats.take_reputation();
+            if *time >= passenger.kind.max_wait(&state.config) && passenger.animation_state == PassengerAnimationState::Idle {
+                take_reputation(&mut state.reputation);
                 passenger.state = PassengerState::Resigned;
-                state.audio.play("resign");
+                // the gate can be on the far side of the board
+                state.audio.emit_at(SoundEvent::Resign, passenger.sprite.centre().x);
             }
         }
     }
 }
 
-pub fn move_passenger(passenger: &mut Passenger, player: &Player, delta: f32) {
+pub fn move_passenger(passenger: &mut Passenger, players: &[Player], config: &GameConfig, delta: f32) {
     if passenger.state == PassengerState::Falling {
-        passenger.sprite.position.y -= delta * PASSENGER_FALL_SPEED;
+        passenger.sprite.position.y -= delta * config.passenger_fall_speed;
         passenger.animation_state = PassengerAnimationState::Falling;
         return
     }
     passenger.animation_state = PassengerAnimationState::Idle;
 
-    let Some(d) = get_walk(passenger, player) else { return };
-    let vx = delta * PASSENGER_WALK_SPEED * d.normalized().x;
+    let Some(d) = get_walk(passenger, players, config) else { return };
+    let vx = delta * config.passenger_walk_speed * d.normalized().x;
     passenger.sprite.position.x += vx.clamp(-d.x.abs(), d.x.abs());
     if !almost_eq(d.len(), 0.) {
         passenger.animation_state = PassengerAnimationState::Walking;
@@ -153,12 +281,14 @@ pub fn move_passenger(passenger: &mut Passenger, player: &Player, delta: f32) {
     }
 }
 
-fn get_walk(passenger: &Passenger, player: &Player) -> Option<Vector2f> {
+fn get_walk(passenger: &Passenger, players: &[Player], config: &GameConfig) -> Option<Vector2f> {
     match passenger.state {
         PassengerState::Waiting(_) => {
-            if should_approach_loading(passenger, player) {
-                return Some(player.sprite.centre() - passenger.sprite.centre());
-            };
+            // approach the closest free taxi
+            return players.iter()
+                .filter(|a| should_approach_loading(passenger, a, config))
+                .map(|a| a.sprite.centre() - passenger.sprite.centre())
+                .min_by(|a, b| a.len().total_cmp(&b.len()));
         },
         PassengerState::Landed(gate) => {
             return Some(gate_centre(gate) - passenger.sprite.centre())
@@ -168,13 +298,13 @@ fn get_walk(passenger: &Passenger, player: &Player) -> Option<Vector2f> {
     None
 }
 
-fn should_approach_loading(passenger: &Passenger, player: &Player) -> bool {
+fn should_approach_loading(passenger: &Passenger, player: &Player, config: &GameConfig) -> bool {
     if player.passenger.is_some() { return false }
     if !same_level(&passenger.sprite, &player.sprite.position) { return false }
 
     if (
         passenger.sprite.centre() - player.sprite.centre()
-    ).len() > PASSENGER_LOAD_DIST {
+    ).len() > config.passenger_load_dist {
         return false
     }
 
@@ -182,70 +312,89 @@ fn should_approach_loading(passenger: &Passenger, player: &Player) -> bool {
 }
 
 pub fn try_load(state: &mut State) {
-    if !state.player.grounded { return }
-    if state.player.passenger.is_some() { return }
-
-    let mut loaded = None;
-    for (i, passenger) in state.passengers.iter_mut().enumerate() {
-        if let PassengerState::Waiting(_) = passenger.state {
-            if !same_level(&passenger.sprite, &state.player.sprite.position) { continue; }
-            if (passenger.sprite.centre() - state.player.sprite.centre()).len()
-                > 0.5 * passenger.sprite.collider_size.x {
+    // when several taxis can take waiting passengers at once,
+    // the closest pairs load first and ties go to the lower player number
+    let mut candidates = Vec::new();
+    for (i, player) in state.players.iter().enumerate() {
+        if !player.grounded { continue }
+        if player.passenger.is_some() { continue }
+        for (j, passenger) in state.passengers.iter().enumerate() {
+            let PassengerState::Waiting(_) = passenger.state else { continue };
+            if !same_level(&passenger.sprite, &player.sprite.position) { continue; }
+            let dist = (passenger.sprite.centre() - player.sprite.centre()).len();
+            if dist > 0.5 * passenger.sprite.collider_size.x {
                 continue;
             }
-            loaded = Some(i);
-            passenger.state = PassengerState::Loaded;
-            state.audio.play("load");
-            break;
+            candidates.push((dist, i, j));
         }
     }
-    if let Some(loaded) = loaded {
-        let passenger = state.passengers.remove(loaded);
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut loaded: Vec<(usize, usize)> = Vec::new();
+    for (_, i, j) in candidates {
+        if loaded.iter().any(|(a, b)| *a == i || *b == j) { continue }
+        loaded.push((i, j));
+    }
+    // remove from the back, so the remaining indices stay valid
+    loaded.sort_by(|a, b| b.1.cmp(&a.1));
+    for (i, j) in loaded {
+        let mut passenger = state.passengers.remove(j);
+        passenger.state = PassengerState::Loaded;
         state.board.gates[passenger.source_gate as usize].clear_passenger();
-        state.player.passenger = Some(passenger);
+        state.players[i].passenger = Some(passenger);
+        state.audio.emit_at(SoundEvent::Load, state.players[i].sprite.centre().x);
     }
 }
 
 pub fn try_knock_down(state: &mut State) {
-    if state.player.v.len() < PASSENGER_KNOCK_DOWN_SPEED { return }
-    let player_aabb = state.player.sprite.aabb();
+    for player in state.players.iter() {
+        if player.v.len() < state.config.knock_down_speed { continue }
+        let player_aabb = player.sprite.aabb();
 
-    for passenger in state.passengers.iter_mut() {
-        if passenger.state == PassengerState::Falling { continue; }
-        if !passenger.sprite.aabb().intersects(&player_aabb) { continue; }
-        if let PassengerState::Waiting(_) = passenger.state {
-            state.board.gates[passenger.source_gate as usize].clear_passenger();
+        for passenger in state.passengers.iter_mut() {
+            if passenger.state == PassengerState::Falling { continue; }
+            if !passenger.sprite.aabb().intersects(&player_aabb) { continue; }
+            if let PassengerState::Waiting(_) = passenger.state {
+                state.board.gates[passenger.source_gate as usize].clear_passenger();
+            }
+            passenger.state = PassengerState::Falling;
+            take_reputation(&mut state.reputation);
+            state.audio.emit_at(SoundEvent::KnockDown, passenger.sprite.centre().x);
         }
-        passenger.state = PassengerState::Falling;
-        state.player.stats.take_reputation();
-        state.audio.play("hit");
     }
 }
 
 pub fn try_unload(state: &mut State) {
-    let gate_no = if let Some(passenger) = &state.player.passenger {
+    for idx in 0..state.players.len() {
+        try_unload_player(state, idx);
+    }
+}
+
+fn try_unload_player(state: &mut State, idx: usize) {
+    let player = &mut state.players[idx];
+    let gate_no = if let Some(passenger) = &player.passenger {
         passenger.target_gate
     } else {
         return;
     };
-    if !state.player.grounded { return }
-    if state.player.v.len() > TOLERANCE { return }
+    if !player.grounded { return }
+    if player.v.len() > TOLERANCE { return }
     let gate_position = if let Some(gate) =  state.board.gates.get(gate_no as usize) {
         gate.position
     } else { 
         return
     };
-    if (state.player.sprite.centre() - gate_centre(gate_position)).len() > PASSENGER_LOAD_DIST {
+    if (player.sprite.centre() - gate_centre(gate_position)).len() > state.config.passenger_load_dist {
         return
     }
-    if !same_level(&state.player.sprite, &gate_position) { return }
+    if !same_level(&player.sprite, &gate_position) { return }
 
-    let mut passenger = state.player.passenger.take().unwrap();
+    let mut passenger = player.passenger.take().unwrap();
     passenger.state = PassengerState::Landed(gate_position);
-    passenger.sprite.position = state.player.sprite.position;
+    passenger.sprite.position = player.sprite.position;
     state.passengers.push(passenger);
-    state.player.stats.score += 1;
-    state.audio.play("unload");
+    player.stats.score += 1;
+    state.audio.emit_at(SoundEvent::Unload, player.sprite.centre().x);
 }
 
 fn same_level(sprite: &DynamicSprite, v: &Vector2f) -> bool {
@@ -3,35 +3,81 @@ use rogalik_math::{
     vectors::Vector2f
 };
 use rogalik_engine::Color;
+use rogalik_engine::input::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
 
-use crate::globals::{
-    GRAVITY_ACC, FLY_MAX_SPEED, LIFT_MAX_SPEED, HOR_DRAG, LIFT_ACC, DAMAGE_SPEED,
-    HIT_IMMUNITY, TOLERANCE
-};
-use crate::passenger::Passenger;
+use crate::audio::SoundEvent;
+use crate::config::GameConfig;
+use crate::globals::TOLERANCE;
+use crate::bindings::{Action, Bindings};
+use crate::input::InputContext;
+use crate::passenger::{Passenger, PassengerState};
 use crate::sprite::DynamicSprite;
 use crate::utils::almost_eq;
 
-use super::State;
+use super::{Context_, State};
 
-#[derive(Default)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct Stats {
     pub stamina: f32,
     pub stamina_use: f32,
-    pub stamina_recovery: f32,
     pub score: u32,
-    pub reputation: u32,
 }
-impl Stats {
-    pub fn take_reputation(&mut self) {
-        self.reputation = self.reputation.saturating_sub(1);
+
+#[derive(Clone, Copy, Default)]
+pub struct Input {
+    // 0 to 1
+    pub lift: f32,
+    // -1 (left) to 1 (right)
+    pub horizontal: f32
+}
+
+pub struct Controls {
+    pub lift: Vec<VirtualKeyCode>,
+    pub left: Vec<VirtualKeyCode>,
+    pub right: Vec<VirtualKeyCode>
+}
+
+pub fn get_controls(bindings: &Bindings, idx: usize, player_count: usize) -> Controls {
+    // a single player can use either key set
+    let players = match player_count {
+        1 => vec![0, 1],
+        _ => vec![idx]
+    };
+    let keys = |action| players.iter().map(|p| bindings.key(*p, action)).collect();
+    Controls {
+        lift: keys(Action::Lift),
+        left: keys(Action::Left),
+        right: keys(Action::Right)
     }
 }
 
-#[derive(Default)]
+pub fn read_input(idx: usize, controls: &Controls, input: &InputContext, context: &Context_) -> Input {
+    // keys give full thrust, the gamepad with the same index is proportional
+    let is_down = |keys: &Vec<VirtualKeyCode>| keys.iter().any(|k| context.input.is_key_down(*k));
+    let lift = match is_down(&controls.lift) {
+        true => 1.,
+        false => input.pad_lift(idx)
+    };
+    let horizontal = match (is_down(&controls.left), is_down(&controls.right)) {
+        (true, false) => -1.,
+        (false, true) => 1.,
+        _ => input.pad_horizontal(idx)
+    };
+    Input { lift, horizontal }
+}
+
+pub fn take_reputation(reputation: &mut u32) {
+    // reputation is shared by all the taxis
+    *reputation = reputation.saturating_sub(1);
+}
+
+#[derive(Default, Deserialize, Serialize)]
 pub struct Player {
     pub sprite: DynamicSprite,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub v: Vector2f,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub a: Vector2f,
     pub grounded: bool,
     pub immunity: f32,
@@ -68,45 +114,84 @@ impl Player {
     }
 }
 
-pub fn try_hit(player: &mut Player) -> bool {
+pub fn try_hit(player: &mut Player, reputation: &mut u32, config: &GameConfig) -> bool {
     if player.immunity > TOLERANCE { return false; }
-    player.immunity = HIT_IMMUNITY;
-    player.stats.take_reputation();
+    player.immunity = config.hit_immunity;
+    take_reputation(reputation);
     true
 }
 
-pub fn handle_lift(player: &mut Player, delta: f32, working: bool) {
-    if !working {
-        player.stats.stamina = 1.0_f32.min(player.stats.stamina + player.stats.stamina_recovery * delta);
+pub fn try_bump(state: &mut State) {
+    // airborne taxis colliding at knock down speed:
+    // the slower one is pushed away and drops its passenger
+    for i in 0..state.players.len() {
+        for j in i + 1..state.players.len() {
+            let (left, right) = state.players.split_at_mut(j);
+            let (a, b) = (&mut left[i], &mut right[0]);
+            if a.grounded || b.grounded { continue }
+            if a.immunity > TOLERANCE || b.immunity > TOLERANCE { continue }
+            if (a.v - b.v).len() < state.config.knock_down_speed { continue }
+            if !a.sprite.aabb().intersects(&b.sprite.aabb()) { continue }
+
+            let (bumper, victim) = if a.v.len() >= b.v.len() { (a, b) } else { (b, a) };
+            let dir = if victim.sprite.centre().x < bumper.sprite.centre().x { -1. } else { 1. };
+            victim.v.x += dir * state.config.bump_speed;
+            victim.immunity = state.config.hit_immunity;
+            if let Some(mut passenger) = victim.passenger.take() {
+                passenger.state = PassengerState::Falling;
+                passenger.sprite.position = victim.sprite.position;
+                state.passengers.push(passenger);
+                take_reputation(&mut state.reputation);
+            }
+            state.audio.emit_at(SoundEvent::Bump, victim.sprite.centre().x);
+            state.effects.hit();
+        }
+    }
+}
+
+pub fn handle_lift(player: &mut Player, delta: f32, lift: f32, config: &GameConfig) {
+    // partial lift blends between falling and the full lift acceleration
+    // recovery comes from the config, as the adaptive tier changes it mid level
+    if lift <= 0. {
+        player.stats.stamina = 1.0_f32.min(player.stats.stamina + config.stamina_recovery * delta);
         return
     }
     if player.stats.stamina > 0. {
-        player.a.y = LIFT_ACC;
-        player.stats.stamina = 0.0_f32.max(player.stats.stamina - player.stats.stamina_use * delta);
+        player.a.y = -config.gravity_acc + lift * (config.lift_acc + config.gravity_acc);
+        player.stats.stamina = 0.0_f32.max(player.stats.stamina - lift * player.stats.stamina_use * delta);
     }
 }
 
 pub fn update_player(state: &mut State, delta: f32) {
-    state.player.immunity = 0.0_f32.max(
-        state.player.immunity - delta
-    );
-    let blink = (state.player.immunity * 10.) as u32 % 2 == 1;
-    state.player.sprite.color.3 = if blink { 0 } else { 255 };
     let obstacles = &state.board.colliders;
-    state.player.v += delta * state.player.a;
-    if move_y(&mut state.player, obstacles, delta) {
-        if try_hit(&mut state.player) {
-            state.audio.play("hit");
+    let config = &state.config;
+    for player in state.players.iter_mut() {
+        let was_grounded = player.grounded;
+        player.immunity = 0.0_f32.max(
+            player.immunity - delta
+        );
+        let blink = (player.immunity * 10.) as u32 % 2 == 1;
+        player.sprite.color.3 = if blink { 0 } else { 255 };
+        player.v += delta * player.a;
+        if move_y(player, obstacles, config, delta) {
+            if try_hit(player, &mut state.reputation, config) {
+                state.audio.emit_at(SoundEvent::Hit, player.sprite.centre().x);
+                state.effects.hit();
+            }
+        }
+        if player.grounded && !was_grounded {
+            let position = Vector2f::new(player.sprite.centre().x, player.sprite.position.y);
+            state.particles.burst("dust", position, 1.);
         }
+        move_x(player, obstacles, config, delta);
     }
-    move_x(&mut state.player, obstacles, delta);
 }
-fn move_y(player: &mut Player, obstacles: &Vec<Aabb>, delta: f32) -> bool {
+fn move_y(player: &mut Player, obstacles: &Vec<Aabb>, config: &GameConfig, delta: f32) -> bool {
     // returns true on damage
     // TODO - make a result struct or smth?
     player.grounded = false;
-    player.v.y = player.v.y.min(LIFT_MAX_SPEED);
-    player.a.y = -GRAVITY_ACC;
+    player.v.y = player.v.y.min(config.lift_max_speed);
+    player.a.y = -config.gravity_acc;
 
     let dy = delta * player.v.y;
     let colliders = collision(
@@ -118,7 +203,7 @@ fn move_y(player: &mut Player, obstacles: &Vec<Aabb>, delta: f32) -> bool {
     }
     let mut damage = false;
     // if collision on high speed, decr. rep
-    if player.v.y.abs() > DAMAGE_SPEED {
+    if player.v.y.abs() > config.damage_speed {
         damage = true;
     }
 
@@ -135,14 +220,14 @@ fn move_y(player: &mut Player, obstacles: &Vec<Aabb>, delta: f32) -> bool {
     player.v.y = 0.;
     damage
 }
-fn move_x(player: &mut Player, obstacles: &Vec<Aabb>, delta: f32) {
+fn move_x(player: &mut Player, obstacles: &Vec<Aabb>, config: &GameConfig, delta: f32) {
     player.a.x = match player.v.x {
-        x if x < -TOLERANCE => HOR_DRAG,
-        x if x > TOLERANCE => -HOR_DRAG,
+        x if x < -TOLERANCE => config.hor_drag,
+        x if x > TOLERANCE => -config.hor_drag,
         _ => 0.,
     };
 
-    player.v.x = player.v.x.clamp(-FLY_MAX_SPEED, FLY_MAX_SPEED);
+    player.v.x = player.v.x.clamp(-config.fly_max_speed, config.fly_max_speed);
     if almost_eq(player.v.x, 0.) { player.v.x = 0. }
     let dx = delta * player.v.x;
 
@@ -0,0 +1,145 @@
+use rogalik_engine::Color;
+use rogalik_math::vectors::Vector2f;
+use serde::{Deserialize, Serialize};
+
+use super::{Context_, GameMode, GameState, State};
+use crate::board::Gate;
+use crate::config;
+use crate::creatures::Creature;
+use crate::difficulty::{self, Adaptive, Difficulty};
+use crate::passenger::{Manifest, Passenger};
+use crate::player::Player;
+use crate::storage;
+use crate::utils::{GameRng, Ticker};
+
+const SAVE_KEY: &str = "run";
+
+// serde mirrors of the engine types
+
+#[derive(Deserialize, Serialize)]
+#[serde(remote = "Vector2f")]
+pub struct Vector2fDef {
+    pub x: f32,
+    pub y: f32
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(remote = "Color")]
+pub struct ColorDef(pub u8, pub u8, pub u8, pub u8);
+
+pub mod atlas {
+    // sprites refer to the atlases by their static names
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::sprite::ATLASES;
+
+    pub fn serialize<S: Serializer>(name: &&'static str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(name)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        ATLASES.iter()
+            .find(|a| a.0 == name)
+            .map(|a| a.0)
+            .ok_or_else(|| D::Error::custom(format!("Unknown atlas: {}", name)))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SavedRun {
+    level: String,
+    mode: GameMode,
+    seed: u64,
+    rng: GameRng,
+    level_time: f32,
+    since_spawn: f32,
+    spawn_interval: f32,
+    reputation: u32,
+    players: Vec<Player>,
+    passengers: Vec<Passenger>,
+    creatures: Vec<Creature>,
+    gates: Vec<Gate>,
+    manifest: Manifest,
+    animation_timer: Ticker,
+    spawn_timer: Ticker,
+    #[serde(default)]
+    difficulty: Difficulty,
+    #[serde(default)]
+    adaptive: Adaptive
+}
+
+pub fn can_save(state: &State) -> bool {
+    // competitive runs can not be suspended, nor daily ones,
+    // as their result belongs to the day they were played
+    !matches!(state.mode, GameMode::TimeAttack | GameMode::Versus | GameMode::Online | GameMode::Daily)
+}
+
+pub fn has_saved_run() -> bool {
+    storage::load::<SavedRun>(SAVE_KEY).is_some()
+}
+
+pub fn save_run(state: &mut State) {
+    // the run is quit afterwards, so its state can be moved out
+    let run = SavedRun {
+        level: state.level.to_string(),
+        mode: state.mode,
+        seed: state.seed,
+        rng: state.rng.clone(),
+        level_time: state.level_time,
+        since_spawn: state.since_spawn,
+        spawn_interval: state.spawn_interval,
+        reputation: state.reputation,
+        players: std::mem::take(&mut state.players),
+        passengers: std::mem::take(&mut state.passengers),
+        creatures: std::mem::take(&mut state.creatures),
+        gates: std::mem::take(&mut state.board.gates),
+        manifest: std::mem::take(&mut state.manifest),
+        animation_timer: std::mem::take(&mut state.animation_timer),
+        spawn_timer: std::mem::take(&mut state.spawn_timer),
+        difficulty: state.difficulty,
+        adaptive: state.adaptive.clone()
+    };
+    storage::save(SAVE_KEY, &run);
+    state.has_saved_run = true;
+}
+
+pub fn resume_run(state: &mut State, context: &mut Context_) {
+    // a saved run can be continued only once,
+    // one that can not be restored is kept
+    let Some(run) = storage::load::<SavedRun>(SAVE_KEY) else {
+        eprintln!("Can't load the saved run");
+        return
+    };
+    let Some((level, _)) = state.level_entry(&run.level) else {
+        eprintln!("Can't resume the saved run, unknown level: {}", run.level);
+        return
+    };
+    state.level = level;
+    state.mode = run.mode;
+    super::reinit(state, context);
+    super::load_level(state, context, level);
+
+    state.seed = run.seed;
+    state.rng = run.rng;
+    state.level_time = run.level_time;
+    state.since_spawn = run.since_spawn;
+    state.spawn_interval = run.spawn_interval;
+    state.reputation = run.reputation;
+    state.players = run.players;
+    state.passengers = run.passengers;
+    state.creatures = run.creatures;
+    state.board.gates = run.gates;
+    state.manifest = run.manifest;
+    state.animation_timer = run.animation_timer;
+    state.spawn_timer = run.spawn_timer;
+    // the run keeps the difficulty it was started with
+    state.config = config::level_config(state);
+    difficulty::start(state, run.difficulty);
+    if run.difficulty == Difficulty::Adaptive {
+        difficulty::apply_tier(&mut state.config, run.adaptive.tier);
+        state.adaptive = run.adaptive;
+    }
+    storage::remove(SAVE_KEY);
+    state.has_saved_run = false;
+    state.game_state = GameState::Play;
+}
@@ -0,0 +1,105 @@
+use rogalik_engine::input::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{Context_, State};
+use crate::storage;
+use crate::utils::current_day;
+
+const TABLE_SIZE: usize = 10;
+const INITIALS_LEN: usize = 3;
+
+const LETTER_KEYS: [(VirtualKeyCode, char); 26] = [
+    (VirtualKeyCode::A, 'A'), (VirtualKeyCode::B, 'B'), (VirtualKeyCode::C, 'C'),
+    (VirtualKeyCode::D, 'D'), (VirtualKeyCode::E, 'E'), (VirtualKeyCode::F, 'F'),
+    (VirtualKeyCode::G, 'G'), (VirtualKeyCode::H, 'H'), (VirtualKeyCode::I, 'I'),
+    (VirtualKeyCode::J, 'J'), (VirtualKeyCode::K, 'K'), (VirtualKeyCode::L, 'L'),
+    (VirtualKeyCode::M, 'M'), (VirtualKeyCode::N, 'N'), (VirtualKeyCode::O, 'O'),
+    (VirtualKeyCode::P, 'P'), (VirtualKeyCode::Q, 'Q'), (VirtualKeyCode::R, 'R'),
+    (VirtualKeyCode::S, 'S'), (VirtualKeyCode::T, 'T'), (VirtualKeyCode::U, 'U'),
+    (VirtualKeyCode::V, 'V'), (VirtualKeyCode::W, 'W'), (VirtualKeyCode::X, 'X'),
+    (VirtualKeyCode::Y, 'Y'), (VirtualKeyCode::Z, 'Z')
+];
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreEntry {
+    pub initials: String,
+    pub day: u64,
+    pub seed: u64,
+    pub delivered: u32
+}
+
+pub struct NewRecord {
+    pub rank: usize,
+    pub initials: String,
+    pub submitted: bool
+}
+
+#[derive(Default)]
+pub struct HighScores {
+    pub tables: HashMap<&'static str, Vec<ScoreEntry>>,
+    pub new_record: Option<NewRecord>
+}
+impl HighScores {
+    pub fn table(&self, level: &str) -> &[ScoreEntry] {
+        self.tables.get(level).map(|a| a.as_slice()).unwrap_or(&[])
+    }
+    pub fn is_entering(&self) -> bool {
+        self.new_record.as_ref().is_some_and(|a| !a.submitted)
+    }
+}
+
+pub fn load_scores<'a>(levels: impl Iterator<Item=&'a &'static str>) -> HighScores {
+    let mut scores = HighScores::default();
+    for level in levels {
+        if let Some(table) = storage::load(&table_key(level)) {
+            scores.tables.insert(*level, table);
+        }
+    }
+    scores
+}
+
+pub fn check_record(state: &mut State) {
+    // called when a run ends, a qualifying score asks for the initials
+    let delivered = state.score();
+    let table = state.high_scores.table(state.level);
+    let rank = table.iter().position(|a| delivered > a.delivered).unwrap_or(table.len());
+    state.high_scores.new_record = match rank < TABLE_SIZE && delivered > 0 {
+        true => Some(NewRecord { rank, initials: String::new(), submitted: false }),
+        false => None
+    };
+}
+
+pub fn handle_initials(state: &mut State, context: &Context_) {
+    let delivered = state.score();
+    let Some(record) = state.high_scores.new_record.as_mut() else { return };
+    if record.submitted { return }
+
+    for (key, c) in LETTER_KEYS {
+        if record.initials.len() < INITIALS_LEN && context.input.is_key_pressed(key) {
+            record.initials.push(c);
+        }
+    }
+    if context.input.is_key_pressed(VirtualKeyCode::Back) {
+        record.initials.pop();
+    }
+    if record.initials.len() == 0 || !context.input.is_key_pressed(VirtualKeyCode::Return) {
+        return
+    }
+
+    record.submitted = true;
+    let entry = ScoreEntry {
+        initials: record.initials.clone(),
+        day: current_day(),
+        seed: state.seed,
+        delivered
+    };
+    let table = state.high_scores.tables.entry(state.level).or_default();
+    table.insert(record.rank.min(table.len()), entry);
+    table.truncate(TABLE_SIZE);
+    storage::save(&table_key(state.level), table);
+}
+
+fn table_key(level: &str) -> String {
+    format!("scores_{}", level.to_lowercase().replace(' ', "_"))
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::Difficulty;
+use crate::storage;
+
+const SETTINGS_KEY: &str = "settings";
+
+pub const PIXEL_SCALES: [f32; 4] = [32., 48., 64., 96.];
+pub const WINDOW_SIZES: [(u32, u32); 4] = [(1024, 640), (1280, 800), (1600, 1000), (1920, 1200)];
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    // volumes are 0 to 1
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub pixel_scale: f32,
+    // window options are applied at startup only
+    pub window_size: (u32, u32),
+    pub fullscreen: bool,
+    pub screen_shake: bool,
+    // also disables the hit-stop and the wipe transitions
+    pub reduce_motion: bool,
+    pub difficulty: Difficulty
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            master_volume: 1.,
+            sfx_volume: 1.,
+            music_volume: 0.7,
+            pixel_scale: 64.,
+            window_size: WINDOW_SIZES[0],
+            fullscreen: false,
+            screen_shake: true,
+            reduce_motion: false,
+            difficulty: Difficulty::Normal
+        }
+    }
+}
+
+pub fn load_settings() -> Settings {
+    storage::load(SETTINGS_KEY).unwrap_or_default()
+}
+
+pub fn save_settings(settings: &Settings) {
+    storage::save(SETTINGS_KEY, settings);
+}
+
+pub fn next_volume(volume: f32) -> f32 {
+    // 10% steps, wrapping to mute after full
+    if volume >= 0.95 { return 0. }
+    ((volume * 10.).round() + 1.) / 10.
+}
+
+pub fn next_option<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    // unknown values (eg. edited by hand) restart the cycle
+    let idx = options.iter().position(|a| *a == current).map_or(0, |a| a + 1);
+    options[idx % options.len()]
+}
+
+pub fn percent(volume: f32) -> String {
+    format!("{}%", (volume * 100.).round() as u32)
+}
+
+pub fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
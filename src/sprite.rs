@@ -3,19 +3,37 @@ use rogalik_math::{
     aabb::Aabb,
     vectors::Vector2f
 };
+use serde::{Deserialize, Serialize};
 
 use crate::globals::TILE_SIZE;
 
-#[derive(Default)]
+// bundled sprite atlases with their grid size (columns and rows)
+pub const ATLASES: [(&str, &[u8], usize); 6] = [
+    ("actors", include_bytes!("../assets/actors.png"), 4),
+    ("creatures", include_bytes!("../assets/creatures.png"), 4),
+    ("tiles", include_bytes!("../assets/tiles.png"), 4),
+    // rock tiles with caps on every side, see assets/autotile.ron
+    ("rocks", include_bytes!("../assets/rocks.png"), 5),
+    ("ui", include_bytes!("../assets/ui.png"), 4),
+    ("ascii", include_bytes!("../assets/ascii.png"), 16)
+];
+
+#[derive(Default, Deserialize, Serialize)]
 pub struct DynamicSprite {
+    #[serde(with = "crate::save::atlas")]
     pub atlas: &'static str,
     pub index: usize,
+    #[serde(with = "crate::save::ColorDef")]
     pub color: Color,
     pub frame: usize,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub position: Vector2f,
     pub z_index: i32,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub collider_size: Vector2f,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub collider_offset: Vector2f,
+    #[serde(with = "crate::save::Vector2fDef")]
     pub size: Vector2f,
     pub flip_x: bool
 }
@@ -56,6 +74,7 @@ impl DynamicSprite {
     }
 }
 
+#[derive(Clone)]
 pub struct StaticSprite {
     pub atlas: &'static str,
     pub index: usize,
@@ -0,0 +1,67 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(target_arch="wasm32")]
+use wasm_bindgen::prelude::*;
+
+// persistent key-value storage:
+// ron files on native and localStorage entries on wasm
+
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let data = read(key)?;
+    ron::from_str(&data).ok()
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let Ok(data) = ron::to_string(value) else { return };
+    write(key, &data);
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub fn remove(key: &str) {
+    let _ = std::fs::remove_file(path(key));
+}
+
+#[cfg(target_arch="wasm32")]
+pub fn remove(key: &str) {
+    local_storage_remove(&format!("grrr_{}", key));
+}
+
+#[cfg(not(target_arch="wasm32"))]
+const SAVE_DIR: &str = "save";
+
+#[cfg(not(target_arch="wasm32"))]
+fn path(key: &str) -> std::path::PathBuf {
+    std::path::Path::new(SAVE_DIR).join(format!("{}.ron", key))
+}
+
+#[cfg(not(target_arch="wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)).ok()
+}
+
+#[cfg(not(target_arch="wasm32"))]
+fn write(key: &str, data: &str) {
+    if std::fs::create_dir_all(SAVE_DIR).is_err() { return }
+    let _ = std::fs::write(path(key), data);
+}
+
+#[cfg(target_arch="wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = localStorage, js_name = getItem)]
+    fn local_storage_get(key: &str) -> Option<String>;
+    #[wasm_bindgen(js_namespace = localStorage, js_name = setItem)]
+    fn local_storage_set(key: &str, value: &str);
+    #[wasm_bindgen(js_namespace = localStorage, js_name = removeItem)]
+    fn local_storage_remove(key: &str);
+}
+
+#[cfg(target_arch="wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage_get(&format!("grrr_{}", key))
+}
+
+#[cfg(target_arch="wasm32")]
+fn write(key: &str, data: &str) {
+    local_storage_set(&format!("grrr_{}", key), data);
+}
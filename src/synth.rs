@@ -0,0 +1,186 @@
+use rand::RngCore;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::utils::GameRng;
+
+// sfxr style generator, used when a bundled sound can not be decoded
+// and for the sounds that have no asset at all
+
+const SAMPLE_RATE: u32 = 44100;
+// noise values per wave period
+const NOISE_STEPS: usize = 32;
+
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Wave {
+    #[default]
+    Square,
+    Saw,
+    Sine,
+    Noise
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct SynthParams {
+    pub wave: Wave,
+    // square wave high share of the period
+    pub duty: f32,
+    // Hz
+    pub base_freq: f32,
+    // the sound ends when a falling slide reaches it
+    pub min_freq: f32,
+    // octaves per second and its change per second
+    pub slide: f32,
+    pub delta_slide: f32,
+    // relative depth and Hz
+    pub vibrato_depth: f32,
+    pub vibrato_speed: f32,
+    // frequency multiplier applied once after `arp_time` seconds
+    pub arp_mult: f32,
+    pub arp_time: f32,
+    // envelope stages in seconds
+    pub attack: f32,
+    pub sustain: f32,
+    pub decay: f32,
+    // extra volume at the sustain start, fading out over it
+    pub punch: f32,
+    pub volume: f32
+}
+impl Default for SynthParams {
+    fn default() -> Self {
+        SynthParams {
+            wave: Wave::Square,
+            duty: 0.5,
+            base_freq: 440.,
+            min_freq: 20.,
+            slide: 0.,
+            delta_slide: 0.,
+            vibrato_depth: 0.,
+            vibrato_speed: 0.,
+            arp_mult: 1.,
+            arp_time: 0.,
+            attack: 0.,
+            sustain: 0.1,
+            decay: 0.2,
+            punch: 0.,
+            volume: 0.5
+        }
+    }
+}
+
+pub fn load_presets() -> HashMap<String, SynthParams> {
+    ron::from_str(include_str!("../assets/synth.ron")).expect("Invalid synth presets!")
+}
+
+pub fn generate(params: &SynthParams) -> Vec<f32> {
+    let length = params.attack + params.sustain + params.decay;
+    let count = (length * SAMPLE_RATE as f32) as usize;
+    let dt = 1. / SAMPLE_RATE as f32;
+    // a fixed seed, so a preset always sounds the same
+    let mut rng = GameRng::new(0);
+    let mut noise = [0.; NOISE_STEPS];
+    let mut phase = 0.;
+    let mut samples = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let t = i as f32 * dt;
+        let octaves = params.slide * t + 0.5 * params.delta_slide * t * t;
+        let mut freq = params.base_freq * 2_f32.powf(octaves);
+        if freq < params.min_freq {
+            if params.slide < 0. { break }
+            freq = params.min_freq;
+        }
+        if params.arp_time > 0. && t >= params.arp_time {
+            freq *= params.arp_mult;
+        }
+        freq *= 1. + params.vibrato_depth * (std::f32::consts::TAU * params.vibrato_speed * t).sin();
+
+        phase += freq * dt;
+        if phase >= 1. {
+            phase -= phase.floor();
+            for value in noise.iter_mut() {
+                *value = 2. * (rng.next_u32() as f32 / u32::MAX as f32) - 1.;
+            }
+        }
+        let value = match params.wave {
+            Wave::Square => if phase < params.duty { 1. } else { -1. },
+            Wave::Saw => 1. - 2. * phase,
+            Wave::Sine => (std::f32::consts::TAU * phase).sin(),
+            Wave::Noise => noise[(phase * NOISE_STEPS as f32) as usize % NOISE_STEPS]
+        };
+        samples.push(params.volume * envelope(params, t) * value);
+    }
+    samples
+}
+
+fn envelope(params: &SynthParams, t: f32) -> f32 {
+    if t < params.attack {
+        return t / params.attack
+    }
+    let t = t - params.attack;
+    if t < params.sustain {
+        return 1. + params.punch * (1. - t / params.sustain)
+    }
+    let t = t - params.sustain;
+    if params.decay <= 0. { return 0. }
+    (1. - t / params.decay).max(0.)
+}
+
+pub fn sequence(params: &SynthParams, notes: &str, step: f32) -> Vec<f32> {
+    // a loop of notes, in semitones from the base frequency and `.` for rests,
+    // every note takes `step` seconds and its tail wraps around to the start
+    let notes = notes.split_whitespace().collect::<Vec<_>>();
+    let step_len = (step * SAMPLE_RATE as f32) as usize;
+    let mut samples = vec![0.; notes.len() * step_len];
+    if samples.is_empty() { return samples }
+    let len = samples.len();
+    for (i, note) in notes.iter().enumerate() {
+        let Ok(semitones) = note.parse::<f32>() else { continue };
+        let params = SynthParams {
+            base_freq: params.base_freq * 2_f32.powf(semitones / 12.),
+            ..params.clone()
+        };
+        for (j, value) in generate(&params).iter().enumerate() {
+            samples[(i * step_len + j) % len] += value;
+        }
+    }
+    samples
+}
+
+pub fn to_wav(samples: &[f32]) -> Vec<u8> {
+    // 16 bit mono pcm
+    let data_size = 2 * samples.len() as u32;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend(b"RIFF");
+    wav.extend((36 + data_size).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16_u32.to_le_bytes());
+    // pcm format, a single channel
+    wav.extend(1_u16.to_le_bytes());
+    wav.extend(1_u16.to_le_bytes());
+    wav.extend(SAMPLE_RATE.to_le_bytes());
+    wav.extend((2 * SAMPLE_RATE).to_le_bytes());
+    // block align and bits per sample
+    wav.extend(2_u16.to_le_bytes());
+    wav.extend(16_u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend(data_size.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1., 1.) * i16::MAX as f32) as i16;
+        wav.extend(value.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub fn export_wavs(dir: &str) -> std::io::Result<()> {
+    // tool mode, run with: --export-sounds <dir>
+    std::fs::create_dir_all(dir)?;
+    for (name, params) in load_presets().iter() {
+        let path = std::path::Path::new(dir).join(format!("{}.wav", name));
+        std::fs::write(&path, to_wav(&generate(params)))?;
+        println!("Written {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use rogalik_engine::Color;
+use rogalik_math::vectors::Vector2f;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::State;
+use crate::globals::{
+    ACTOR_FRAMES, ANIMATION_TICK, GHOST_SAMPLE, PLAYER_Z, TILE_SIZE, TIME_ATTACK_DELIVERIES
+};
+use crate::passenger::{Manifest, ManifestEntry, PassengerKind, PassengerState};
+use crate::sprite::DynamicSprite;
+use crate::storage;
+
+const GHOST_COLOR: Color = Color(255, 255, 255, 96);
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Record {
+    pub time: f32,
+    // level time of every delivery
+    pub splits: Vec<f32>,
+    // player positions sampled every GHOST_SAMPLE seconds
+    pub ghost: Vec<(f32, f32)>
+}
+
+#[derive(Default)]
+pub struct TimeAttack {
+    pub records: HashMap<&'static str, Record>,
+    pub current: Record,
+    pub ghost: DynamicSprite,
+    pub new_record: bool,
+    // manifest entries handed out so far, replacements included
+    issued: u32,
+    since_sample: f32
+}
+impl TimeAttack {
+    pub fn best(&self, level: &str) -> Option<&Record> {
+        self.records.get(level)
+    }
+}
+
+pub fn load_records<'a>(levels: impl Iterator<Item=&'a &'static str>) -> TimeAttack {
+    let mut time_attack = TimeAttack::default();
+    for level in levels {
+        if let Some(record) = storage::load(&record_key(level)) {
+            time_attack.records.insert(*level, record);
+        }
+    }
+    time_attack
+}
+
+pub fn start(state: &mut State) {
+    // every run gets the same deliveries, spawned as soon as the gates are free
+    state.manifest = fixed_manifest(state.board.gates.len() as u32);
+    state.time_attack.issued = state.manifest.entries.len() as u32;
+    state.time_attack.current = Record::default();
+    state.time_attack.new_record = false;
+    state.time_attack.since_sample = GHOST_SAMPLE;
+    state.time_attack.ghost = DynamicSprite::new(
+        state.players[0].sprite.position,
+        PLAYER_Z - 1,
+        "actors",
+        0,
+        GHOST_COLOR,
+        Vector2f::new(TILE_SIZE, TILE_SIZE),
+        Vector2f::ZERO
+    );
+}
+
+pub fn is_finished(state: &State) -> bool {
+    state.score() >= TIME_ATTACK_DELIVERIES
+}
+
+pub fn finish(state: &mut State) {
+    let time_attack = &mut state.time_attack;
+    time_attack.current.time = state.level_time;
+    let is_best = match time_attack.records.get(state.level) {
+        Some(best) => time_attack.current.time < best.time,
+        None => true
+    };
+    if !is_best { return }
+    time_attack.new_record = true;
+    storage::save(&record_key(state.level), &time_attack.current);
+    time_attack.records.insert(state.level, time_attack.current.clone());
+}
+
+pub fn update(state: &mut State, delta: f32) {
+    replace_lost(state);
+
+    let time_attack = &mut state.time_attack;
+    if state.score() as usize > time_attack.current.splits.len() {
+        time_attack.current.splits.push(state.level_time);
+    }
+
+    time_attack.since_sample += delta;
+    if time_attack.since_sample >= GHOST_SAMPLE {
+        time_attack.since_sample -= GHOST_SAMPLE;
+        let position = state.players[0].sprite.position;
+        time_attack.current.ghost.push((position.x, position.y));
+    }
+
+    let Some(best) = time_attack.records.get(state.level) else { return };
+    if let Some(position) = ghost_position(best, state.level_time) {
+        time_attack.ghost.position = position;
+    }
+    time_attack.ghost.frame = (state.level_time / ANIMATION_TICK) as usize % ACTOR_FRAMES;
+}
+
+pub fn hud_text(state: &State) -> String {
+    let current = &state.time_attack.current;
+    let mut text = format!(
+        "{}/{}  {:.1}s",
+        current.splits.len(),
+        TIME_ATTACK_DELIVERIES,
+        state.level_time
+    );
+    let Some(split) = current.splits.last() else { return text };
+    let idx = current.splits.len() - 1;
+    if let Some(best) = state.time_attack.best(state.level).and_then(|a| a.splits.get(idx)) {
+        text += &format!("  {:+.1}", split - best);
+    }
+    text
+}
+
+fn ghost_position(record: &Record, time: f32) -> Option<Vector2f> {
+    let last = record.ghost.last()?;
+    let t = time / GHOST_SAMPLE;
+    let idx = t.floor() as usize;
+    if idx + 1 >= record.ghost.len() {
+        return Some(Vector2f::new(last.0, last.1));
+    }
+    let a = Vector2f::new(record.ghost[idx].0, record.ghost[idx].1);
+    let b = Vector2f::new(record.ghost[idx + 1].0, record.ghost[idx + 1].1);
+    Some(a + (t - idx as f32) * (b - a))
+}
+
+fn replace_lost(state: &mut State) {
+    // a resigned or knocked down passenger is replaced by the next fixed entry,
+    // so the run can always be finished
+    let gate_count = state.board.gates.len() as u32;
+    if gate_count < 2 { return }
+    let waiting = state.passengers.iter()
+        .filter(|a| matches!(a.state, PassengerState::Waiting(_)))
+        .count();
+    let carried = state.players.iter()
+        .filter(|a| a.passenger.is_some())
+        .count();
+    let pending = (state.manifest.entries.len() + waiting + carried) as u32;
+    let needed = TIME_ATTACK_DELIVERIES.saturating_sub(state.score());
+    for _ in pending..needed {
+        let entry = fixed_entry(state.time_attack.issued, gate_count);
+        state.manifest.entries.push_back(entry);
+        state.time_attack.issued += 1;
+    }
+}
+
+fn fixed_manifest(gate_count: u32) -> Manifest {
+    let mut manifest = Manifest::default();
+    if gate_count < 2 { return manifest }
+    for i in 0..TIME_ATTACK_DELIVERIES {
+        manifest.entries.push_back(fixed_entry(i, gate_count));
+    }
+    manifest
+}
+
+fn fixed_entry(i: u32, gate_count: u32) -> ManifestEntry {
+    // cycle through all the source gates, shifting the targets every round
+    let source_gate = i % gate_count;
+    let shift = 1 + (i / gate_count) % (gate_count - 1);
+    ManifestEntry {
+        time: 0.,
+        source_gate,
+        target_gate: (source_gate + shift) % gate_count,
+        kind: PassengerKind::Regular
+    }
+}
+
+fn record_key(level: &str) -> String {
+    format!("time_attack_{}", level.to_lowercase().replace(' ', "_"))
+}
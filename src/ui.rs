@@ -1,25 +1,60 @@
 use rogalik_engine::{Color, GraphicsContext, Params2d, ResourceId};
 use rogalik_math::vectors::Vector2f;
 
-use super::{Context_, State};
+use super::{Context_, GameMode, GameState, State};
+use crate::audio::SoundEvent;
+use crate::bindings::{Action, SLOTS};
+use crate::input::Command;
 use crate::globals::{
-    TILE_SIZE, PIXEL_SCALE, BASE_REPUTATION, PASSENGER_MAX_WAIT, UI_Z, UI_BG_Z
+    TILE_SIZE, UI_Z, UI_BG_Z
 };
 use crate::passenger::PassengerState;
-use crate::utils::{to_roman, pixel_perfect};
+use crate::settings::{
+    next_option, next_volume, on_off, percent, save_settings, PIXEL_SCALES, WINDOW_SIZES
+};
+use crate::player::Player;
+use crate::utils::{current_day, date_string, to_roman, pixel_perfect};
 
 const UI_BG: Color = Color(85, 113, 119, 255);
 const UI_RED: Color = Color(152, 77, 77, 255);
+const MENU_ROWS: usize = 5;
+
+#[derive(Default)]
+pub struct MenuFocus {
+    // index of the focused widget on the current screen
+    pub focus: usize,
+    // the binding waiting for a key press
+    capture: Option<(usize, Action)>,
+    confirm: bool,
+    mouse: Vector2f,
+    mouse_moved: bool
+}
 
 pub fn render_game_ui(state: &State, context: &mut Context_) {
     render_passenger_targets(state, context);
     render_status_bar(state, context);
+    let mode_text = match state.mode {
+        GameMode::Campaign(idx) => state.campaign.levels.get(idx)
+            .map(|a| crate::campaign::objective_text(a, state)),
+        GameMode::TimeAttack => Some(crate::time_attack::hud_text(state)),
+        GameMode::Versus => Some(crate::versus::hud_text(state)),
+        _ => None
+    };
+    // the difficulty is shown only when it differs from normal
+    let text = [mode_text, crate::difficulty::hud_text(state)]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("  ");
+    if text.len() > 0 {
+        render_corner_text(&text, state, context);
+    }
 }
 
 fn render_passenger_targets(state: &State, context: &mut Context_) {
     for passenger in state.passengers.iter() {
         if let PassengerState::Waiting(time) = passenger.state {
-            let idx = if time > 0.5 * PASSENGER_MAX_WAIT {
+            let idx = if time > 0.5 * passenger.kind.max_wait(&state.config) {
                 2
             } else {
                 0
@@ -28,7 +63,8 @@ fn render_passenger_targets(state: &State, context: &mut Context_) {
                 "ui",
                 idx,
                 pixel_perfect(
-                    passenger.sprite.centre() + Vector2f::new(- 0.4 * TILE_SIZE, 0.8 * TILE_SIZE)
+                    passenger.sprite.centre() + Vector2f::new(- 0.4 * TILE_SIZE, 0.8 * TILE_SIZE),
+                    state.settings.pixel_scale
                 ),
                 UI_BG_Z,
                 Vector2f::new(0.8 * TILE_SIZE, 0.5 * TILE_SIZE),
@@ -40,7 +76,8 @@ fn render_passenger_targets(state: &State, context: &mut Context_) {
                 "default",
                 t,
                 pixel_perfect(
-                    passenger.sprite.centre() + Vector2f::new(-dx, 0.9 * TILE_SIZE)
+                    passenger.sprite.centre() + Vector2f::new(-dx, 0.9 * TILE_SIZE),
+                    state.settings.pixel_scale
                 ),
                 UI_Z,
                 0.25 * TILE_SIZE,
@@ -51,17 +88,16 @@ fn render_passenger_targets(state: &State, context: &mut Context_) {
 }
 
 fn render_status_bar(state: &State, context: &mut Context_) {
-    let top = context.get_logical_size().y / PIXEL_SCALE;
+    let top = view_size(state, context).y;
     let base = Vector2f::new(0.25, top - 0.5 * TILE_SIZE);
-    let stamina_width = 4. * TILE_SIZE;
     let height = 0.25 * TILE_SIZE;
     let margin = 0.25 * TILE_SIZE;
     let mut offset = 0.;
 
-    // draw reputation
+    // draw shared reputation
 
-    for i in 0..crate::globals::BASE_REPUTATION {
-        let color = if i >= state.player.stats.reputation { UI_BG } else { UI_RED };
+    for i in 0..state.config.reputation {
+        let color = if i >= state.reputation { UI_BG } else { UI_RED };
         context.graphics.draw_atlas_sprite(
             "ascii",
             3,
@@ -71,7 +107,30 @@ fn render_status_bar(state: &State, context: &mut Context_) {
             Params2d { color, ..Default::default() }
         );
     }
-    offset += BASE_REPUTATION as f32 * height + margin;
+    offset += state.config.reputation as f32 * height + margin;
+
+    for (i, player) in state.players.iter().enumerate() {
+        let label = match state.players.len() {
+            1 => "Score".to_string(),
+            _ => format!("P{}", i + 1)
+        };
+        offset += render_player_status(player, &label, base + Vector2f::new(offset, 0.), state, context)
+            + 2. * margin;
+    }
+}
+
+fn render_player_status(
+    player: &Player,
+    label: &str,
+    base: Vector2f,
+    state: &State,
+    context: &mut Context_
+) -> f32 {
+    // returns the offset after the last element
+    let stamina_width = 4. * TILE_SIZE / state.players.len() as f32;
+    let height = 0.25 * TILE_SIZE;
+    let margin = 0.25 * TILE_SIZE;
+    let mut offset = 0.;
 
     // draw stamina bar
     context.graphics.draw_atlas_sprite(
@@ -82,20 +141,20 @@ fn render_status_bar(state: &State, context: &mut Context_) {
         Vector2f::new(stamina_width, 1.5 * height),
         Params2d { slice: Some((4, Vector2f::new(1., 1.))), ..Default::default() }
     );
-    if state.player.stats.stamina * stamina_width > 8. / crate::globals::PIXEL_SCALE {
+    if player.stats.stamina * stamina_width > 8. / state.settings.pixel_scale {
         context.graphics.draw_atlas_sprite(
             "ui",
             2,
             base + Vector2f::new(offset, -0.25 * height),
             UI_Z,
-            Vector2f::new(state.player.stats.stamina * stamina_width, 1.5 * height),
+            Vector2f::new(player.stats.stamina * stamina_width, 1.5 * height),
             Params2d { slice: Some((2, Vector2f::new(1., 1.))), ..Default::default() }
         );
     };
     offset += stamina_width + margin;
 
     // draw score
-    let score_text = format!("Score: {}", state.player.stats.score);
+    let score_text = format!("{}: {}", label, player.stats.score);
     context.graphics.draw_text(
         "default",
         &score_text,
@@ -107,7 +166,7 @@ fn render_status_bar(state: &State, context: &mut Context_) {
     offset += margin + context.graphics.text_dimensions("default", &score_text, height).x;
 
     // draw load status
-    if let Some(passenger) = &state.player.passenger {
+    if let Some(passenger) = &player.passenger {
         context.graphics.draw_atlas_sprite(
             "ui",
             0,
@@ -125,31 +184,285 @@ fn render_status_bar(state: &State, context: &mut Context_) {
             context
         );
     }
+    offset + 3. * height
+}
+
+fn render_corner_text(text: &str, state: &State, context: &mut Context_) {
+    // top right counterpart of the status bar
+    let height = 0.25 * TILE_SIZE;
+    let width = context.graphics.text_dimensions("default", text, height).x;
+    let vs = view_size(state, context);
+    context.graphics.draw_text(
+        "default",
+        text,
+        Vector2f::new(vs.x - 0.25 - width, vs.y - 0.5 * TILE_SIZE),
+        UI_Z,
+        height,
+        Params2d { color: UI_BG, ..Default::default() }
+    );
+}
+
+pub fn render_debug_panel(state: &State, context: &mut Context_) {
+    // live balance values, see config::update_debug_panel for the keys
+    if !state.debug.visible { return }
+    let height = 0.2 * TILE_SIZE;
+    let vs = view_size(state, context);
+    let base = Vector2f::new(0.25, vs.y - 1.25 * TILE_SIZE);
+    let header = if cfg!(target_arch="wasm32") {
+        "F1 hide  PgUp/PgDn select  -/= change"
+    } else {
+        "F1 hide  PgUp/PgDn select  -/= change  F2 save level  F3 save base"
+    };
+    context.graphics.draw_text(
+        "default",
+        header,
+        base,
+        UI_Z,
+        height,
+        Params2d { color: UI_BG, ..Default::default() }
+    );
+    for (i, (name, _)) in crate::config::FIELDS.iter().enumerate() {
+        let color = if i == state.debug.selected { UI_RED } else { UI_BG };
+        let value = state.config.get(name).unwrap_or(0.);
+        // unsaved edits are starred
+        let mark = if state.debug.edits.contains_key(name) { "*" } else { "" };
+        context.graphics.draw_text(
+            "default",
+            &format!("{}: {:.2}{}", name, value, mark),
+            base - Vector2f::new(0., (i + 1) as f32 * 1.5 * height),
+            UI_Z,
+            height,
+            Params2d { color, ..Default::default() }
+        );
+    }
+}
+
+pub fn render_pause_menu(state: &mut State, context: &mut Context_) {
+    let vs = view_size(state, context);
+    render_centered_text(
+        Vector2f::new(0.5 * vs.x, vs.y - 2. * TILE_SIZE),
+        "PAUSED",
+        TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
+
+    // a daily challenge can not be restarted
+    let can_restart = state.mode != GameMode::Daily;
+    let can_save = crate::save::can_save(state);
+    let mut entries = vec!["Resume"];
+    if can_restart { entries.push("Restart") }
+    if can_save { entries.push("Save and quit") }
+    entries.push("Quit to menu");
+
+    let count = entries.len();
+    navigate(count, state, context);
+    for (i, entry) in entries.into_iter().enumerate() {
+        let button = menu_button(i, count, entry, state, context);
+        if !focusable(button, i, state, context) { continue }
+        match entry {
+            "Resume" => state.game_state = GameState::Play,
+            "Restart" => state.game_state = GameState::Init,
+            "Save and quit" => {
+                crate::save::save_run(state);
+                state.game_state = GameState::MainMenu;
+            },
+            _ => {
+                if state.mode == GameMode::Daily {
+                    crate::daily::finish(state);
+                }
+                state.game_state = GameState::MainMenu;
+            }
+        }
+        return
+    }
+}
+
+pub fn render_level_complete(state: &State, context: &mut Context_) {
+    let vs = view_size(state, context);
+    let centre = Vector2f::new(
+        0.5 * vs.x,
+        0.5 * vs.y,
+    );
+    if state.mode == GameMode::TimeAttack {
+        render_time_attack_result(centre, state, context);
+        return
+    }
+    render_centered_text(centre, "LEVEL COMPLETE", TILE_SIZE, UI_BG, state, context);
+
+    let size = 0.5 * TILE_SIZE;
+    for i in 0..3 {
+        let color = if i < state.campaign.last_stars { UI_RED } else { UI_BG };
+        render_centered_text(
+            centre + Vector2f::new((i as f32 - 1.) * 2. * size, -TILE_SIZE * 1.25),
+            "*",
+            size,
+            color,
+            state,
+            context
+        );
+    }
+    render_centered_text(
+        centre - Vector2f::new(0., TILE_SIZE * 2.),
+        &confirm_prompt(state),
+        0.5 *TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
+}
+
+fn render_time_attack_result(centre: Vector2f, state: &State, context: &mut Context_) {
+    render_centered_text(centre, "FINISHED", TILE_SIZE, UI_BG, state, context);
+    let time_attack = &state.time_attack;
+    let (text, color) = match time_attack.best(state.level) {
+        _ if time_attack.new_record => (
+            format!("New record: {:.2}s", time_attack.current.time), UI_RED
+        ),
+        Some(best) => (
+            format!("Time: {:.2}s  Best: {:.2}s", time_attack.current.time, best.time), UI_BG
+        ),
+        None => (format!("Time: {:.2}s", time_attack.current.time), UI_BG)
+    };
+    render_centered_text(
+        centre - Vector2f::new(0., TILE_SIZE * 1.25),
+        &text,
+        0.5 * TILE_SIZE,
+        color,
+        state,
+        context
+    );
+    render_centered_text(
+        centre - Vector2f::new(0., TILE_SIZE * 2.),
+        &confirm_prompt(state),
+        0.5 *TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
+}
+
+pub fn render_versus_result(state: &State, context: &mut Context_) {
+    let vs = view_size(state, context);
+    let centre = Vector2f::new(
+        0.5 * vs.x,
+        0.5 * vs.y,
+    );
+    let title = match crate::versus::winner(state) {
+        Some(idx) => format!("PLAYER {} WINS", idx + 1),
+        None => "DRAW".to_string()
+    };
+    render_centered_text(centre, &title, TILE_SIZE, UI_BG, state, context);
+    let scores = state.players.iter()
+        .enumerate()
+        .map(|(i, a)| format!("P{}: {}", i + 1, a.stats.score))
+        .collect::<Vec<_>>()
+        .join("   ");
+    render_centered_text(
+        centre - Vector2f::new(0., TILE_SIZE * 1.25),
+        &scores,
+        0.5 *TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
+    render_centered_text(
+        centre - Vector2f::new(0., TILE_SIZE * 2.),
+        &confirm_prompt(state),
+        0.5 *TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
 }
 
 pub fn render_game_over(state: &State, context: &mut Context_) {
-    let vs = context.get_logical_size() / PIXEL_SCALE;
+    let vs = view_size(state, context);
     let centre = Vector2f::new(
         0.5 * vs.x,
         0.5 * vs.y,
     );
     render_centered_text(centre, "GAME OVER", TILE_SIZE, UI_BG, state, context);
+    if state.mode == GameMode::Daily {
+        render_centered_text(
+            centre + Vector2f::new(0., TILE_SIZE * 1.25),
+            &format!("Daily challenge {}", date_string(current_day())),
+            0.5 * TILE_SIZE,
+            UI_BG,
+            state,
+            context
+        );
+    }
     render_centered_text(
         centre - Vector2f::new(0., TILE_SIZE * 1.25),
-        &format!("Passengers delivered: {}", state.player.stats.score),
+        &format!("Passengers delivered: {}", state.score()),
         0.5 *TILE_SIZE,
         UI_BG,
         state,
         context
     );
+    let (prompt, color) = match &state.high_scores.new_record {
+        Some(record) if !record.submitted => (
+            format!("NEW RECORD! Initials: {:_<3} (enter)", record.initials), UI_RED
+        ),
+        _ => (confirm_prompt(state), UI_BG)
+    };
     render_centered_text(
         centre - Vector2f::new(0., TILE_SIZE * 2.),
-        "(press spacebar)",
+        &prompt,
         0.5 *TILE_SIZE,
-        UI_BG,
+        color,
         state,
         context
     );
+    if state.mode == GameMode::FreePlay {
+        let highlight = state.high_scores.new_record.as_ref()
+            .filter(|a| a.submitted)
+            .map(|a| a.rank);
+        render_score_table(
+            state.level,
+            centre - Vector2f::new(4.5 * TILE_SIZE, 2.75 * TILE_SIZE),
+            5,
+            highlight,
+            state,
+            context
+        );
+    }
+}
+
+fn render_score_table(
+    level: &str,
+    base: Vector2f,
+    rows: usize,
+    highlight: Option<usize>,
+    state: &State,
+    context: &mut Context_
+) {
+    // entries fill the columns top to bottom
+    let height = 0.2 * TILE_SIZE;
+    let column_width = 4.5 * TILE_SIZE;
+    for (i, entry) in state.high_scores.table(level).iter().enumerate() {
+        let color = if Some(i) == highlight { UI_RED } else { UI_BG };
+        let text = format!(
+            "{:>2} {:<3} {:>3} {}",
+            i + 1,
+            entry.initials,
+            entry.delivered,
+            date_string(entry.day)
+        );
+        context.graphics.draw_text(
+            "default",
+            &text,
+            base + Vector2f::new(
+                (i / rows) as f32 * column_width,
+                -((i % rows) as f32) * 1.5 * height
+            ),
+            UI_Z,
+            height,
+            Params2d { color, ..Default::default() }
+        );
+    }
 }
 
 fn render_centered_text(
@@ -172,12 +485,305 @@ fn render_centered_text(
 }
 
 pub fn render_main_menu(state: &mut State, context: &mut Context_) {
-    let button_height = TILE_SIZE;
-    let button_width = TILE_SIZE * 8.;
-    let vs = context.get_logical_size();
+    render_title(state, context);
+
+    let modes = [
+        ("Campaign", GameMode::Campaign(0)),
+        ("Free play", GameMode::FreePlay),
+        ("Time attack", GameMode::TimeAttack),
+        ("Co-op", GameMode::CoOp),
+        ("Versus", GameMode::Versus)
+    ];
+    // an optional continue entry comes first,
+    // the daily challenge, difficulty, settings and the online entry follow the modes
+    let first = state.has_saved_run as usize;
+    let count = first + modes.len() + if cfg!(target_arch="wasm32") { 3 } else { 4 };
+    navigate(count, state, context);
+
+    if state.has_saved_run {
+        let button = menu_button(0, count, "Continue", state, context);
+        if focusable(button, 0, state, context) {
+            crate::save::resume_run(state, context);
+            return
+        }
+    }
+
+    for (i, (text, mode)) in modes.iter().enumerate() {
+        let button = menu_button(first + i, count, text, state, context);
+        if focusable(button, first + i, state, context) {
+            state.mode = *mode;
+            state.game_state = GameState::LevelSelect;
+        }
+    }
+
+    let (text, color) = match state.daily.today() {
+        Some(result) => (format!("Daily: {} delivered", result.score), UI_BG),
+        None => ("Daily challenge".to_string(), Color(255, 255, 255, 255))
+    };
+    let button = menu_button(first + modes.len(), count, &text, state, context).with_color(color);
+    if focusable(button, first + modes.len(), state, context) {
+        crate::daily::start(state);
+    }
+
+    let text = format!("Difficulty: {}", state.settings.difficulty.name());
+    let button = menu_button(first + modes.len() + 1, count, &text, state, context);
+    if focusable(button, first + modes.len() + 1, state, context) {
+        state.settings.difficulty = state.settings.difficulty.next();
+        save_settings(&state.settings);
+    }
+
+    let button = menu_button(first + modes.len() + 2, count, "Settings", state, context);
+    if focusable(button, first + modes.len() + 2, state, context) {
+        state.game_state = GameState::Settings;
+    }
+
+    #[cfg(not(target_arch="wasm32"))]
+    render_online_button(first + modes.len() + 3, count, state, context);
+}
+
+#[cfg(not(target_arch="wasm32"))]
+fn render_online_button(idx: usize, count: usize, state: &mut State, context: &mut Context_) {
+    // the peer is given on the command line with --host or --join
+    let Some(config) = state.net_config.clone() else {
+        let button = menu_button(idx, count, "Online (no peer)", state, context).with_color(UI_BG);
+        focusable(button, idx, state, context);
+        return
+    };
+    let button = menu_button(idx, count, "Online co-op", state, context);
+    if !focusable(button, idx, state, context) { return }
+    state.mode = GameMode::Online;
+    state.game_state = match config {
+        crate::netplay::Config::Host(_) => GameState::LevelSelect,
+        crate::netplay::Config::Join(_) => GameState::Connecting
+    };
+}
+
+pub fn render_connecting(state: &State, context: &mut Context_) {
+    let vs = view_size(state, context);
+    let centre = Vector2f::new(
+        0.5 * vs.x,
+        0.5 * vs.y,
+    );
+    render_centered_text(centre, "Waiting for the other player", 0.5 * TILE_SIZE, UI_BG, state, context);
+    render_centered_text(
+        centre - Vector2f::new(0., TILE_SIZE * 1.),
+        "(press escape to cancel)",
+        0.5 *TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
+}
+
+pub fn render_level_select(state: &mut State, context: &mut Context_) {
+    render_title(state, context);
+
+    let count = match state.mode {
+        GameMode::Campaign(_) => state.campaign.levels.len(),
+        _ => state.level_data.len()
+    };
+    navigate(count + 1, state, context);
+    match state.mode {
+        GameMode::Campaign(_) => render_campaign_levels(state, context),
+        _ => render_free_levels(state, context)
+    }
+
+    let button = menu_button(count, count + 1, "Back", state, context);
+    if focusable(button, count, state, context) || state.input.is_pressed(Command::Back, context) {
+        state.game_state = GameState::MainMenu;
+    }
+}
+
+fn render_campaign_levels(state: &mut State, context: &mut Context_) {
+    for i in 0..state.campaign.levels.len() {
+        let unlocked = state.campaign.is_unlocked(i);
+        let text = format!(
+            "{} {}",
+            state.campaign.levels[i].level,
+            "*".repeat(state.campaign.stars(i) as usize)
+        );
+        let mut button = menu_button(i, state.campaign.levels.len() + 1, &text, state, context);
+        if !unlocked {
+            button = button.with_color(UI_BG);
+        }
+        if !focusable(button, i, state, context) || !unlocked { continue }
+        let Some((level, _)) = state.level_entry(&state.campaign.levels[i].level) else { continue };
+        state.level = level;
+        state.mode = GameMode::Campaign(i);
+        state.game_state = GameState::Init;
+    }
+}
+
+fn render_free_levels(state: &mut State, context: &mut Context_) {
+    for i in 0..state.level_data.len() {
+        let level = state.level_data[i].0;
+        let best_score = state.high_scores.table(level).first().map(|a| a.delivered);
+        let text = match (state.mode, state.time_attack.best(level), best_score) {
+            (GameMode::TimeAttack, Some(record), _) => format!("{} {:.1}s", level, record.time),
+            (GameMode::FreePlay, _, Some(score)) => format!("{} {}", level, score),
+            _ => level.to_string()
+        };
+        let button = menu_button(i, state.level_data.len() + 1, &text, state, context);
+        let activated = focusable(button, i, state, context);
+        if state.mode == GameMode::FreePlay && state.menu.focus == i {
+            let vs = view_size(state, context);
+            render_score_table(
+                level,
+                Vector2f::new(0.25 * TILE_SIZE, vs.y - 3.25 * TILE_SIZE),
+                10,
+                None,
+                state,
+                context
+            );
+        }
+        if activated {
+            state.level = level;
+            state.game_state = match state.mode {
+                GameMode::Online => GameState::Connecting,
+                _ => GameState::Init
+            };
+        }
+    }
+}
+
+pub fn render_settings(state: &mut State, context: &mut Context_) {
+    render_title(state, context);
+
+    // every entry steps through its values when activated,
+    // the ones marked with * apply after a restart
+    let settings = &state.settings;
+    let (width, height) = settings.window_size;
+    let entries = [
+        format!("Volume: {}", percent(settings.master_volume)),
+        format!("Sounds: {}", percent(settings.sfx_volume)),
+        format!("Music: {}", percent(settings.music_volume)),
+        format!("Pixel scale: {}", settings.pixel_scale),
+        format!("Window: {}x{}*", width, height),
+        format!("Fullscreen: {}*", on_off(settings.fullscreen)),
+        format!("Screen shake: {}", on_off(settings.screen_shake)),
+        format!("Reduce motion: {}", on_off(settings.reduce_motion)),
+        "Controls".to_string(),
+        "Back".to_string()
+    ];
+    let count = entries.len();
+    navigate(count, state, context);
+
+    let mut activated = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let button = menu_button(i, count, entry, state, context);
+        if focusable(button, i, state, context) {
+            activated = Some(i);
+        }
+    }
+    if state.input.is_pressed(Command::Back, context) {
+        activated = Some(count - 1);
+    }
+    let Some(idx) = activated else { return };
+
+    let settings = &mut state.settings;
+    match idx {
+        0 => settings.master_volume = next_volume(settings.master_volume),
+        1 => settings.sfx_volume = next_volume(settings.sfx_volume),
+        2 => settings.music_volume = next_volume(settings.music_volume),
+        3 => settings.pixel_scale = next_option(&PIXEL_SCALES, settings.pixel_scale),
+        4 => settings.window_size = next_option(&WINDOW_SIZES, settings.window_size),
+        5 => settings.fullscreen = !settings.fullscreen,
+        6 => settings.screen_shake = !settings.screen_shake,
+        7 => settings.reduce_motion = !settings.reduce_motion,
+        8 => {
+            state.game_state = GameState::Controls;
+            return
+        },
+        _ => {
+            state.game_state = GameState::MainMenu;
+            return
+        }
+    }
+    save_settings(&state.settings);
+    state.audio.set_volume(
+        state.settings.master_volume,
+        state.settings.sfx_volume,
+        state.settings.music_volume
+    );
+    if idx == 3 {
+        crate::create_camera(state, context);
+    }
+}
+
+pub fn render_controls(state: &mut State, context: &mut Context_) {
+    render_title(state, context);
+    if let Some((player, action)) = state.menu.capture {
+        capture_key(player, action, state, context);
+        return
+    }
+
+    // the bindings are followed by reset and back
+    let count = SLOTS.len() + 2;
+    navigate(count, state, context);
+    for (i, (player, action)) in SLOTS.into_iter().enumerate() {
+        let text = crate::bindings::slot_text(player, action, &state.input.bindings);
+        let button = menu_button(i, count, &text, state, context);
+        if focusable(button, i, state, context) {
+            state.menu.capture = Some((player, action));
+        }
+    }
+
+    let button = menu_button(SLOTS.len(), count, "Reset defaults", state, context);
+    if focusable(button, SLOTS.len(), state, context) {
+        state.input.bindings = Default::default();
+        crate::bindings::save_bindings(&state.input.bindings);
+    }
+    let button = menu_button(SLOTS.len() + 1, count, "Back", state, context);
+    if focusable(button, SLOTS.len() + 1, state, context) || state.input.is_pressed(Command::Back, context) {
+        state.game_state = GameState::Settings;
+    }
+}
+
+fn capture_key(player: usize, action: Action, state: &mut State, context: &mut Context_) {
+    let vs = view_size(state, context);
+    let centre = Vector2f::new(
+        0.5 * vs.x,
+        0.5 * vs.y,
+    );
+    render_centered_text(
+        centre,
+        &format!("Press a key for {}", crate::bindings::slot_name(player, action)),
+        0.5 * TILE_SIZE,
+        UI_BG,
+        state,
+        context
+    );
+    // escape cancels, unless it is the key being chosen for pause
+    let cancellable = action != Action::Pause;
+    if cancellable {
+        render_centered_text(
+            centre - Vector2f::new(0., TILE_SIZE * 1.),
+            "(press escape to cancel)",
+            0.5 *TILE_SIZE,
+            UI_BG,
+            state,
+            context
+        );
+    }
+
+    let Some(key) = crate::bindings::pressed_key(context) else { return };
+    if !cancellable || key != rogalik_engine::input::VirtualKeyCode::Escape {
+        state.input.bindings.rebind(player, action, key);
+        crate::bindings::save_bindings(&state.input.bindings);
+    }
+    state.menu.capture = None;
+}
+
+fn confirm_prompt(state: &State) -> String {
+    let key = crate::bindings::key_label(state.input.bindings.confirm);
+    format!("(press {})", key.to_lowercase())
+}
+
+fn render_title(state: &State, context: &mut Context_) {
+    let vs = view_size(state, context);
     let top = Vector2f::new(
-        0.5 * vs.x / PIXEL_SCALE,
-        vs.y / PIXEL_SCALE,
+        0.5 * vs.x,
+        vs.y,
     );
 
     render_centered_text(
@@ -188,26 +794,75 @@ pub fn render_main_menu(state: &mut State, context: &mut Context_) {
         state,
         context
     );
+}
+
+fn navigate(count: usize, state: &mut State, context: &Context_) {
+    // moves the keyboard and gamepad focus, columns are switched with left / right
+    if count == 0 { return }
+    let input = &state.input;
+    let menu = &mut state.menu;
+    if input.is_pressed(Command::Down, context) {
+        menu.focus = (menu.focus + 1) % count;
+    }
+    if input.is_pressed(Command::Up, context) {
+        menu.focus = (menu.focus + count - 1) % count;
+    }
+    if input.is_pressed(Command::Right, context) && menu.focus + MENU_ROWS < count {
+        menu.focus += MENU_ROWS;
+    }
+    if input.is_pressed(Command::Left, context) && menu.focus >= MENU_ROWS {
+        menu.focus -= MENU_ROWS;
+    }
+    menu.focus = menu.focus.min(count - 1);
+    menu.confirm = input.is_pressed(Command::Confirm, context);
+
+    // a still mouse should not steal the focus from the keyboard
+    let m = context.input.get_mouse_physical_position();
+    menu.mouse_moved = m.x != menu.mouse.x || m.y != menu.mouse.y;
+    menu.mouse = m;
+}
 
+fn focusable(button: Button, idx: usize, state: &mut State, context: &mut Context_) -> bool {
+    // draws the button, returns true when it is clicked or confirmed while focused
+    if state.menu.mouse_moved && button.hovered(state.camera_main, context) {
+        state.menu.focus = idx;
+    }
+    let button = button.with_focus(state.menu.focus == idx);
+    button.draw(state, context);
+    let activated = button.clicked(state.camera_main, context) || (button.focused && state.menu.confirm);
+    if activated {
+        state.audio.emit(SoundEvent::Select);
+    }
+    activated
+}
+
+pub fn view_size(state: &State, context: &Context_) -> Vector2f {
+    // the logical window size in world units
+    context.get_logical_size() / state.settings.pixel_scale
+}
+
+fn menu_button(idx: usize, count: usize, text: &str, state: &State, context: &Context_) -> Button {
+    // long menus wrap into side by side columns
+    let button_height = TILE_SIZE;
+    let button_width = TILE_SIZE * 7.;
+    let columns = (count + MENU_ROWS - 1) / MENU_ROWS;
+    let column = idx / MENU_ROWS;
+    let row = idx % MENU_ROWS;
+    let vs = view_size(state, context);
     let base = Vector2f::new(
-        top.x - 0.5 * button_width,
-        top.y - 3.5 * TILE_SIZE
+        0.5 * vs.x
+            + (column as f32 - 0.5 * columns as f32) * (button_width + 0.5 * TILE_SIZE)
+            + 0.25 * TILE_SIZE,
+        vs.y - 3.5 * TILE_SIZE
     );
-    for (i, level) in state.level_data.keys().enumerate() {
-        let button = Button::new(
-                base.x,
-                base.y - i as f32 * 1.25 * button_height,
-                button_width,
-                button_height
-            )
-            .with_text(level.to_string())
-            .with_color(Color(255, 255, 255, 255));
-        button.draw(state, context);
-        if button.clicked(state.camera_main, context) {
-            state.level = level;
-            state.game_state = super::GameState::Init;
-        }
-    }
+    Button::new(
+            base.x,
+            base.y - row as f32 * 1.25 * button_height,
+            button_width,
+            button_height
+        )
+        .with_text(text.to_string())
+        .with_color(Color(255, 255, 255, 255))
 }
 
 #[derive(Default)]
@@ -216,7 +871,8 @@ pub struct Button {
     w: f32,
     h: f32,
     text: String,
-    color: Color
+    color: Color,
+    focused: bool
 }
 impl Button {
     pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
@@ -235,10 +891,14 @@ impl Button {
         self.color = color;
         self
     }
+    pub fn with_focus(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
     pub fn draw(&self, state: &State, context: &mut Context_) {
         context.graphics.draw_atlas_sprite(
             "ui",
-            0,
+            if self.focused { 2 } else { 0 },
             self.origin,
             UI_BG_Z,
             Vector2f::new(self.w, self.h),
@@ -254,9 +914,12 @@ impl Button {
         );
     }
     pub fn clicked(&self, camera_id: ResourceId, context: &Context_) -> bool {
-        if !context.input.is_mouse_button_down(rogalik_engine::input::MouseButton::Left) { 
+        if !context.input.is_mouse_button_pressed(rogalik_engine::input::MouseButton::Left) { 
             return false;
         }
+        self.hovered(camera_id, context)
+    }
+    pub fn hovered(&self, camera_id: ResourceId, context: &Context_) -> bool {
         if let Some(camera) = context.graphics.get_camera(camera_id) {
             let m = context.input.get_mouse_physical_position();
             let v = camera.camera_to_world(m);
@@ -1,6 +1,11 @@
+use rand::RngCore;
 use rogalik_math::vectors::Vector2f;
+use serde::{Deserialize, Serialize};
 use crate::globals::TOLERANCE;
 
+#[cfg(target_arch="wasm32")]
+use wasm_bindgen::prelude::*;
+
 pub fn almost_eq(a: f32, b: f32) -> bool {
     (a - b).abs() <= TOLERANCE
 }
@@ -22,7 +27,112 @@ pub fn to_roman(a: u32) -> &'static str {
     }
 }
 
-pub fn pixel_perfect(v: Vector2f) -> Vector2f {
-    let scale = crate::globals::PIXEL_SCALE;
+pub fn from_roman(a: &str) -> Option<u32> {
+    (1..=10).find(|i| to_roman(*i) == a)
+}
+
+pub fn pixel_perfect(v: Vector2f, scale: f32) -> Vector2f {
     Vector2f::new((v.x * scale).round() / scale, (v.y * scale).round() / scale)
-}
\ No newline at end of file
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct Ticker {
+    // a timer advanced by the simulation, so it stops with it
+    interval: f32,
+    elapsed: f32
+}
+impl Ticker {
+    pub fn new(interval: f32) -> Self {
+        Self { interval, elapsed: 0. }
+    }
+    pub fn tick(&mut self, delta: f32) -> bool {
+        // returns true once per passed interval
+        self.elapsed += delta;
+        if self.elapsed < self.interval { return false }
+        self.elapsed -= self.interval;
+        true
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct GameRng {
+    // splitmix64, small enough to be stored and replayed
+    state: u64
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub fn current_day() -> u64 {
+    // days since the unix epoch, in UTC so everyone shares the same day
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|a| a.as_secs() / 86400)
+        .unwrap_or(0)
+}
+
+#[cfg(target_arch="wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn js_date_now() -> f64;
+    #[wasm_bindgen(js_namespace = document, js_name = hasFocus)]
+    fn js_document_has_focus() -> bool;
+}
+
+#[cfg(target_arch="wasm32")]
+pub fn current_day() -> u64 {
+    (js_date_now() / 86_400_000.) as u64
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub fn focus_lost(_context: &crate::Context_) -> bool {
+    // the engine does not report focus changes to the game, and stalled frames
+    // are no reliable sign of them, so native builds only pause on the key
+    false
+}
+
+#[cfg(target_arch="wasm32")]
+pub fn focus_lost(_context: &crate::Context_) -> bool {
+    // the page knows when another tab or window takes the focus
+    !js_document_has_focus()
+}
+
+pub fn date_string(day: u64) -> String {
+    // civil date from days since the epoch
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02}", y, m, d)
+}
@@ -0,0 +1,24 @@
+use super::State;
+use crate::globals::{VERSUS_SCORE_TARGET, VERSUS_TIME_LIMIT};
+
+pub fn is_finished(state: &State) -> bool {
+    state.level_time >= VERSUS_TIME_LIMIT
+        || state.players.iter().any(|a| a.stats.score >= VERSUS_SCORE_TARGET)
+}
+
+pub fn winner(state: &State) -> Option<usize> {
+    // None on a draw
+    let best = state.players.iter().map(|a| a.stats.score).max()?;
+    let mut leaders = state.players.iter()
+        .enumerate()
+        .filter(|(_, a)| a.stats.score == best)
+        .map(|(i, _)| i);
+    let winner = leaders.next();
+    if leaders.next().is_some() { return None }
+    winner
+}
+
+pub fn hud_text(state: &State) -> String {
+    let left = (VERSUS_TIME_LIMIT - state.level_time).max(0.).ceil() as u32;
+    format!("First to {}  {}:{:02}", VERSUS_SCORE_TARGET, left / 60, left % 60)
+}